@@ -80,11 +80,79 @@ impl NefariousWindow {
         self.window_start..=self.window_start + 191
     }
 
+    /// First slot at or after `slot` that starts `len` consecutive slots of the window without a
+    /// nefarious leader, or `None` if no such run fits before the window ends.
+    pub fn next_safe_run(&self, slot: u64, len: u64) -> Option<u64> {
+        let last = *self.valid_land_range().end();
+        let mut start = slot.max(self.window_start);
+        while start.checked_add(len.saturating_sub(1))? <= last {
+            match (start..start + len).find(|&s| self.is_nefarious(s)) {
+                // skip to the leader after the nefarious one
+                Some(nefarious) => start = nefarious - (nefarious - self.window_start) % 4 + 4,
+                None => return Some(start),
+            }
+        }
+        None
+    }
+
     pub fn empty() -> NefariousWindow {
         NefariousWindow { window_start: 0, nefarious: [0; 6] }
     }
 }
 
+/// Return data of the report-if-nefarious instruction: a single byte set via `set_return_data`.
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ReportResult {
+    NotNefarious = 0,
+    Nefarious = 1,
+    /// The program could not read the `Clock` sysvar.
+    Error = 2,
+}
+
+impl ReportResult {
+    /// Parse the return data of the report instruction. `None` unless it is exactly one known
+    /// byte.
+    pub fn from_return_data(data: &[u8]) -> Option<Self> {
+        match data {
+            [byte] => Self::try_from(*byte).ok(),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<u8> for ReportResult {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, u8> {
+        match value {
+            0 => Ok(ReportResult::NotNefarious),
+            1 => Ok(ReportResult::Nefarious),
+            2 => Ok(ReportResult::Error),
+            other => Err(other),
+        }
+    }
+}
+
+/// `ProgramError::Custom` codes returned by the program.
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// `abort_if_nefarious` ran on a leader flagged in its window.
+    NefariousLeader = 100,
+}
+
+impl TryFrom<u32> for ErrorCode {
+    type Error = u32;
+
+    fn try_from(value: u32) -> Result<Self, u32> {
+        match value {
+            100 => Ok(ErrorCode::NefariousLeader),
+            other => Err(other),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -170,6 +238,42 @@ mod tests {
         assert_eq!(original, decoded);
     }
 
+    #[test]
+    fn report_result() {
+        for report in [ReportResult::NotNefarious, ReportResult::Nefarious, ReportResult::Error] {
+            assert_eq!(ReportResult::from_return_data(&[report as u8]), Some(report));
+        }
+        assert_eq!(ReportResult::from_return_data(&[3]), None);
+        assert_eq!(ReportResult::from_return_data(&[]), None);
+        assert_eq!(ReportResult::from_return_data(&[0, 0]), None);
+    }
+
+    #[test]
+    fn next_safe_run() {
+        let mut bits = [0u8; 6];
+        set_bit(&mut bits, 1); // 350_000_004..350_000_007
+        set_bit(&mut bits, 3); // 350_000_012..350_000_015
+        let win = NefariousWindow { window_start: 350_000_000, nefarious: bits };
+
+        assert_eq!(win.next_safe_run(350_000_000, 4), Some(350_000_000));
+        assert_eq!(win.next_safe_run(350_000_001, 4), Some(350_000_008));
+        assert_eq!(win.next_safe_run(350_000_005, 8), Some(350_000_016));
+        assert_eq!(win.next_safe_run(349_999_000, 1), Some(350_000_000));
+        assert_eq!(win.next_safe_run(350_000_190, 2), Some(350_000_190));
+        assert_eq!(win.next_safe_run(350_000_190, 3), None);
+
+        let all = NefariousWindow { window_start: 350_000_000, nefarious: [0xFF; 6] };
+        assert_eq!(all.next_safe_run(350_000_000, 1), None);
+    }
+
+    #[test]
+    fn error_code() {
+        let code = ErrorCode::NefariousLeader as u32;
+        assert_eq!(code, 100);
+        assert_eq!(ErrorCode::try_from(code), Ok(ErrorCode::NefariousLeader));
+        assert_eq!(ErrorCode::try_from(0x1771), Err(0x1771));
+    }
+
     proptest! {
         #[test]
         fn prop_pack_unpack(start in 350_000_000u64..360_000_000,
@@ -1,15 +1,13 @@
 use anti_sandwich_sdk::abort_if_nefarious;
+use anti_sandwich_sdk::outcome::{classify_transaction_error, TransactionFailure};
 use eyre::eyre;
-use litesvm::types::FailedTransactionMetadata;
 use litesvm::LiteSVM;
-use solana_instruction::error::InstructionError;
 use solana_keypair::Keypair;
 use solana_program::instruction::Instruction;
-use solana_program::message::Message;
+use solana_program::message::{Message, VersionedMessage};
 use solana_program::native_token::sol_to_lamports;
 use solana_signer::Signer;
 use solana_transaction::Transaction;
-use solana_transaction_error::TransactionError;
 use std::path::Path;
 
 const COMPILED_PROGRAM_PATH: &str = "target/deploy/anti_sandwich_program.so";
@@ -43,11 +41,9 @@ fn run(svm: &mut LiteSVM, ix: &Instruction, slot: u64, expect_abort: bool) -> ey
 
     svm.warp_to_slot(slot);
 
-    let res = svm.send_transaction(Transaction::new(
-        &[&user],
-        Message::new(&[ix.clone()], Some(&user.pubkey())),
-        svm.latest_blockhash(),
-    ));
+    let message = Message::new(&[ix.clone()], Some(&user.pubkey()));
+    let res =
+        svm.send_transaction(Transaction::new(&[&user], message.clone(), svm.latest_blockhash()));
 
     match (res, expect_abort) {
         (Ok(res), true) => {
@@ -60,12 +56,18 @@ fn run(svm: &mut LiteSVM, ix: &Instruction, slot: u64, expect_abort: bool) -> ey
             )
         }
         (Err(err), true) => {
-            println!(
-                "tx {} aborted (errno={}), as expected. CU={}",
-                err.meta.signature,
-                custom_err_str(&err),
-                err.meta.compute_units_consumed
-            )
+            match classify_transaction_error(&err.err, &VersionedMessage::Legacy(message)) {
+                TransactionFailure::Aborted { index } => println!(
+                    "tx {} aborted by instruction {}, as expected. CU={}",
+                    err.meta.signature, index, err.meta.compute_units_consumed
+                ),
+                failure => {
+                    println!(
+                        "!! tx {} failed but not by the guard: {failure:?} !!",
+                        err.meta.signature
+                    )
+                }
+            }
         }
         (Err(err), false) => {
             println!("!! tx {} aborted but expected success !!", err.meta.signature)
@@ -74,12 +76,3 @@ fn run(svm: &mut LiteSVM, ix: &Instruction, slot: u64, expect_abort: bool) -> ey
 
     Ok(())
 }
-
-fn custom_err_str(meta: &FailedTransactionMetadata) -> String {
-    match meta.err {
-        TransactionError::InstructionError(_, InstructionError::Custom(v)) => {
-            format!("{v}")
-        }
-        _ => meta.err.to_string(),
-    }
-}
@@ -3,7 +3,7 @@ mod simulator;
 
 use crate::sample_jupiter_response::get_sample;
 use crate::simulator::Simulator;
-use anti_sandwich_sdk::adjust_slippage_at_runtime;
+use anti_sandwich_sdk::swap::{protect_swap, Protection};
 use solana_keypair::Keypair;
 use solana_program::pubkey;
 use solana_program::pubkey::Pubkey;
@@ -34,24 +34,22 @@ async fn run_single(sim_lands_on_nefarious: bool) -> eyre::Result<()> {
     // sample swap routes through 3 AMMs for WSOL -> USDC
     let swap_ix_resp = get_sample(&user.pubkey(), (QUOTE_AMOUNT * 1e6) as u64, BASE_SLIPPAGE)?;
 
-    let mut ixs = swap_ix_resp.compute_budget_instructions;
-    ixs.extend(swap_ix_resp.setup_instructions);
-
     let nefarious_leader_slots = if sim_lands_on_nefarious { vec![SIM_SLOT] } else { vec![] };
-    let adjusted_jupiter_tx = adjust_slippage_at_runtime(
+    let protected = protect_swap(
         &nefarious_leader_slots,
-        SLIPPAGE_IF_NEFARIOUS,
-        swap_ix_resp.swap_instruction,
+        swap_ix_resp,
+        Protection::AdjustSlippage { slippage_if_nefarious: SLIPPAGE_IF_NEFARIOUS },
     )?;
-    ixs.push(adjusted_jupiter_tx);
-    if let Some(cleanup) = swap_ix_resp.cleanup_instruction {
-        ixs.push(cleanup);
-    }
 
     sim.set_slot_and_time(SIM_SLOT, SIM_TIMESTAMP);
 
     let res = sim
-        .run(&ixs, &user, AMOUNT_IN_SOL * 2.0, Some(&swap_ix_resp.address_lookup_table_addresses))
+        .run(
+            &protected.instructions,
+            &user,
+            AMOUNT_IN_SOL * 2.0,
+            Some(&protected.address_lookup_table_addresses),
+        )
         .await?;
     let balance = sim.token_balance(user.pubkey(), USDC_MINT);
 
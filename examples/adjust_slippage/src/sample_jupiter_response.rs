@@ -1,3 +1,4 @@
+use anti_sandwich_sdk::jupiter::RouteArgs;
 use jupiter_swap_api_client::swap::PrioritizationType::ComputeBudget;
 use jupiter_swap_api_client::swap::SwapInstructionsResponse;
 use solana_instruction::{AccountMeta, Instruction};
@@ -433,14 +434,10 @@ pub fn get_sample(
         simulation_error: None,
     };
 
-    let slippage_bytes = slippage.to_le_bytes();
-    let quote_bytes = quote_amount.to_le_bytes();
-
-    let data_len = resp.swap_instruction.data.len();
-    resp.swap_instruction.data[data_len - 3] = slippage_bytes[0];
-    resp.swap_instruction.data[data_len - 2] = slippage_bytes[1];
-
-    resp.swap_instruction.data[data_len - 11..data_len - 3].copy_from_slice(&quote_bytes);
+    let mut route = RouteArgs::decode(&resp.swap_instruction.data)?;
+    route.slippage_bps = slippage;
+    route.quoted_out_amount = quote_amount;
+    resp.swap_instruction.data = route.encode();
 
     Ok(resp)
 }
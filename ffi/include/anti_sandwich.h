@@ -0,0 +1,133 @@
+/* Generated by cbindgen from anti-sandwich-ffi. Do not edit. */
+
+#ifndef ANTI_SANDWICH_H
+#define ANTI_SANDWICH_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Packed length of a `NefariousWindow`.
+#define AS_WINDOW_LEN 14
+
+// Length of abort and report instruction data: discriminator and packed window.
+#define AS_WINDOW_DATA_LEN (1 + AS_WINDOW_LEN)
+
+// Length of the adjust-slippage prefix in front of the Jupiter instruction data.
+#define AS_ADJUST_PREFIX_LEN ((1 + AS_WINDOW_LEN) + 2)
+
+typedef enum AsStatus {
+  AS_STATUS_OK = 0,
+  // A required pointer argument is null.
+  AS_STATUS_NULL_POINTER = 1,
+  // The output buffer is too small; `out_len` holds the required length.
+  AS_STATUS_BUFFER_TOO_SMALL = 2,
+  AS_STATUS_SLOT_OUT_OF_WINDOW = 3,
+  AS_STATUS_WINDOW_MISALIGNED = 4,
+  // The bytes are not a packed window.
+  AS_STATUS_INVALID_WINDOW = 5,
+  AS_STATUS_EMPTY_INPUT = 6,
+  // The Jupiter data is not a supported route instruction.
+  AS_STATUS_UNSUPPORTED_TARGET_INSTRUCTION = 7,
+  // The Jupiter route data is malformed.
+  AS_STATUS_INVALID_INSTRUCTION_DATA = 8,
+  AS_STATUS_INSTRUCTION_TOO_LARGE = 9,
+  // Any other SDK error.
+  AS_STATUS_INTERNAL = 10,
+} AsStatus;
+
+// `NefariousWindow` with the same fields.
+typedef struct AsWindow {
+  uint64_t window_start;
+  uint8_t nefarious[6];
+} AsWindow;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Build a window from nefarious leader slots. The window starts at the lowest slot; an empty
+// list yields the empty window.
+//
+// # Safety
+// `slots` must be valid for `len` reads (or null with `len == 0`) and `out` must be writable.
+enum AsStatus as_window_build(const uint64_t *slots, size_t len, struct AsWindow *out);
+
+// Build a window starting at `window_start`, which must be aligned to a leader rotation.
+//
+// # Safety
+// `slots` must be valid for `len` reads (or null with `len == 0`) and `out` must be writable.
+enum AsStatus as_window_build_at(uint64_t window_start,
+                                 const uint64_t *slots,
+                                 size_t len,
+                                 struct AsWindow *out);
+
+// Pack `window` into its 14-byte wire format.
+//
+// # Safety
+// `window` must point to a valid `AsWindow` and `out` must be valid for `out_cap` writes.
+enum AsStatus as_window_pack(const struct AsWindow *window,
+                             uint8_t *out,
+                             size_t out_cap,
+                             size_t *out_len);
+
+// Unpack a 14-byte window.
+//
+// # Safety
+// `data` must be valid for `len` reads and `out` must be writable.
+enum AsStatus as_window_unpack(const uint8_t *data, size_t len, struct AsWindow *out);
+
+// Whether the leader of `slot` is flagged in `window`.
+//
+// # Safety
+// `window` must point to a valid `AsWindow` and `out` must be writable.
+enum AsStatus as_window_is_nefarious(const struct AsWindow *window, uint64_t slot, bool *out);
+
+// Instruction data of `abort_if_nefarious` for `window`.
+//
+// # Safety
+// `window` must point to a valid `AsWindow`, `out` must be valid for `out_cap` writes and
+// `out_len` must be writable.
+enum AsStatus as_abort_data(const struct AsWindow *window,
+                            uint8_t *out,
+                            size_t out_cap,
+                            size_t *out_len);
+
+// Instruction data of `report_if_nefarious` for `window`.
+//
+// # Safety
+// `window` must point to a valid `AsWindow`, `out` must be valid for `out_cap` writes and
+// `out_len` must be writable.
+enum AsStatus as_report_data(const struct AsWindow *window,
+                             uint8_t *out,
+                             size_t out_cap,
+                             size_t *out_len);
+
+// Prefix Jupiter v6 route instruction data with the adjust-slippage discriminator, `window` and
+// `slippage_if_nefarious`. The result is the data of an anti-sandwich program instruction that
+// takes the accounts of the Jupiter instruction.
+//
+// # Safety
+// `window` must point to a valid `AsWindow`, `jupiter_data` must be valid for `jupiter_len`
+// reads, `out` must be valid for `out_cap` writes and `out_len` must be writable.
+enum AsStatus as_adjust_slippage_data(const struct AsWindow *window,
+                                      uint16_t slippage_if_nefarious,
+                                      const uint8_t *jupiter_data,
+                                      size_t jupiter_len,
+                                      uint8_t *out,
+                                      size_t out_cap,
+                                      size_t *out_len);
+
+// The 32-byte upstream program id.
+//
+// # Safety
+// `out` must be valid for 32 writes.
+enum AsStatus as_program_id(uint8_t *out);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* ANTI_SANDWICH_H */
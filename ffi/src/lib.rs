@@ -0,0 +1,436 @@
+//! C ABI for building windows and anti-sandwich instruction data.
+//!
+//! Every function returns an [`AsStatus`] and writes its result through out-pointers into
+//! buffers owned by the caller; nothing is allocated on the Rust side. Variable-length outputs
+//! take the buffer capacity and report the written (or, on `AS_STATUS_BUFFER_TOO_SMALL`, the
+//! required) length through `out_len`.
+//!
+//! The header lives in `include/anti_sandwich.h`. It is checked in rather than written by a build
+//! script; `UPDATE_HEADER=1 cargo test -p anti-sandwich-ffi header` regenerates it, and the same
+//! test fails when it drifts from the exported functions.
+
+use anti_sandwich_common::NefariousWindow;
+use anti_sandwich_sdk::{
+    abort_if_nefarious, adjust_slippage_at_runtime, build_window_at, report_if_nefarious,
+    AntiSandwichError, WindowSource, JUPITER_V6, PROGRAM_ID,
+};
+use solana_instruction::Instruction;
+use std::{ptr, slice};
+
+/// Packed length of a `NefariousWindow`.
+pub const AS_WINDOW_LEN: usize = 14;
+/// Length of abort and report instruction data: discriminator and packed window.
+pub const AS_WINDOW_DATA_LEN: usize = 1 + AS_WINDOW_LEN;
+/// Length of the adjust-slippage prefix in front of the Jupiter instruction data.
+pub const AS_ADJUST_PREFIX_LEN: usize = 1 + AS_WINDOW_LEN + 2;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AsStatus {
+    Ok = 0,
+    /// A required pointer argument is null.
+    NullPointer = 1,
+    /// The output buffer is too small; `out_len` holds the required length.
+    BufferTooSmall = 2,
+    SlotOutOfWindow = 3,
+    WindowMisaligned = 4,
+    /// The bytes are not a packed window.
+    InvalidWindow = 5,
+    EmptyInput = 6,
+    /// The Jupiter data is not a supported route instruction.
+    UnsupportedTargetInstruction = 7,
+    /// The Jupiter route data is malformed.
+    InvalidInstructionData = 8,
+    InstructionTooLarge = 9,
+    /// Any other SDK error.
+    Internal = 10,
+}
+
+impl From<AntiSandwichError> for AsStatus {
+    fn from(err: AntiSandwichError) -> Self {
+        match err {
+            AntiSandwichError::SlotOutOfWindow { .. } => AsStatus::SlotOutOfWindow,
+            AntiSandwichError::WindowMisaligned { .. } => AsStatus::WindowMisaligned,
+            AntiSandwichError::EmptyInput(_) => AsStatus::EmptyInput,
+            AntiSandwichError::UnsupportedTargetInstruction { .. } => {
+                AsStatus::UnsupportedTargetInstruction
+            }
+            AntiSandwichError::InvalidInstructionData(_) => AsStatus::InvalidInstructionData,
+            AntiSandwichError::InstructionTooLarge { .. } => AsStatus::InstructionTooLarge,
+            _ => AsStatus::Internal,
+        }
+    }
+}
+
+/// `NefariousWindow` with the same fields.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AsWindow {
+    pub window_start: u64,
+    pub nefarious: [u8; 6],
+}
+
+impl From<AsWindow> for NefariousWindow {
+    fn from(window: AsWindow) -> Self {
+        NefariousWindow { window_start: window.window_start, nefarious: window.nefarious }
+    }
+}
+
+impl From<NefariousWindow> for AsWindow {
+    fn from(window: NefariousWindow) -> Self {
+        AsWindow { window_start: window.window_start, nefarious: window.nefarious }
+    }
+}
+
+/// Unwrap `$e` or return its error as an [`AsStatus`].
+macro_rules! try_status {
+    ($e:expr) => {
+        match $e {
+            Ok(value) => value,
+            Err(status) => return AsStatus::from(status),
+        }
+    };
+}
+
+/// # Safety
+/// `data` must be null or valid for `len` reads. A null `data` is only accepted with `len == 0`.
+unsafe fn input<'a, T>(data: *const T, len: usize) -> Result<&'a [T], AsStatus> {
+    match (data.is_null(), len) {
+        (true, 0) => Ok(&[]),
+        (true, _) => Err(AsStatus::NullPointer),
+        (false, _) => Ok(slice::from_raw_parts(data, len)),
+    }
+}
+
+/// # Safety
+/// `window` must be null or point to a valid `AsWindow`.
+unsafe fn window(window: *const AsWindow) -> Result<NefariousWindow, AsStatus> {
+    window.as_ref().map(|w| NefariousWindow::from(*w)).ok_or(AsStatus::NullPointer)
+}
+
+/// Copy `data` into the caller's buffer.
+///
+/// # Safety
+/// `out` must be null or valid for `out_cap` writes; `out_len` must be null or writable.
+unsafe fn output(data: &[u8], out: *mut u8, out_cap: usize, out_len: *mut usize) -> AsStatus {
+    if out_len.is_null() {
+        return AsStatus::NullPointer;
+    }
+    *out_len = data.len();
+    if data.len() > out_cap {
+        return AsStatus::BufferTooSmall;
+    } else if out.is_null() {
+        return AsStatus::NullPointer;
+    }
+    ptr::copy_nonoverlapping(data.as_ptr(), out, data.len());
+    AsStatus::Ok
+}
+
+/// Build a window from nefarious leader slots. The window starts at the lowest slot; an empty
+/// list yields the empty window.
+///
+/// # Safety
+/// `slots` must be valid for `len` reads (or null with `len == 0`) and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn as_window_build(
+    slots: *const u64,
+    len: usize,
+    out: *mut AsWindow,
+) -> AsStatus {
+    let slots = try_status!(input(slots, len));
+    let Some(out) = out.as_mut() else {
+        return AsStatus::NullPointer;
+    };
+    *out = try_status!(slots.window()).into();
+    AsStatus::Ok
+}
+
+/// Build a window starting at `window_start`, which must be aligned to a leader rotation.
+///
+/// # Safety
+/// `slots` must be valid for `len` reads (or null with `len == 0`) and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn as_window_build_at(
+    window_start: u64,
+    slots: *const u64,
+    len: usize,
+    out: *mut AsWindow,
+) -> AsStatus {
+    let slots = try_status!(input(slots, len));
+    let Some(out) = out.as_mut() else {
+        return AsStatus::NullPointer;
+    };
+    *out = try_status!(build_window_at(window_start, slots)).into();
+    AsStatus::Ok
+}
+
+/// Pack `window` into its 14-byte wire format.
+///
+/// # Safety
+/// `window` must point to a valid `AsWindow` and `out` must be valid for `out_cap` writes.
+#[no_mangle]
+pub unsafe extern "C" fn as_window_pack(
+    window: *const AsWindow,
+    out: *mut u8,
+    out_cap: usize,
+    out_len: *mut usize,
+) -> AsStatus {
+    let window = try_status!(self::window(window));
+    output(&window.pack_to_vec(), out, out_cap, out_len)
+}
+
+/// Unpack a 14-byte window.
+///
+/// # Safety
+/// `data` must be valid for `len` reads and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn as_window_unpack(
+    data: *const u8,
+    len: usize,
+    out: *mut AsWindow,
+) -> AsStatus {
+    let data = try_status!(input(data, len));
+    let Some(out) = out.as_mut() else {
+        return AsStatus::NullPointer;
+    };
+    let Some(window) = NefariousWindow::unpack(data) else {
+        return AsStatus::InvalidWindow;
+    };
+    *out = window.into();
+    AsStatus::Ok
+}
+
+/// Whether the leader of `slot` is flagged in `window`.
+///
+/// # Safety
+/// `window` must point to a valid `AsWindow` and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn as_window_is_nefarious(
+    window: *const AsWindow,
+    slot: u64,
+    out: *mut bool,
+) -> AsStatus {
+    let window = try_status!(self::window(window));
+    let Some(out) = out.as_mut() else {
+        return AsStatus::NullPointer;
+    };
+    *out = window.is_nefarious(slot);
+    AsStatus::Ok
+}
+
+/// Instruction data of `abort_if_nefarious` for `window`.
+///
+/// # Safety
+/// `window` must point to a valid `AsWindow`, `out` must be valid for `out_cap` writes and
+/// `out_len` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn as_abort_data(
+    window: *const AsWindow,
+    out: *mut u8,
+    out_cap: usize,
+    out_len: *mut usize,
+) -> AsStatus {
+    let window = try_status!(self::window(window));
+    let ix = try_status!(abort_if_nefarious(&window));
+    output(&ix.data, out, out_cap, out_len)
+}
+
+/// Instruction data of `report_if_nefarious` for `window`.
+///
+/// # Safety
+/// `window` must point to a valid `AsWindow`, `out` must be valid for `out_cap` writes and
+/// `out_len` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn as_report_data(
+    window: *const AsWindow,
+    out: *mut u8,
+    out_cap: usize,
+    out_len: *mut usize,
+) -> AsStatus {
+    let window = try_status!(self::window(window));
+    output(&report_if_nefarious(window).data, out, out_cap, out_len)
+}
+
+/// Prefix Jupiter v6 route instruction data with the adjust-slippage discriminator, `window` and
+/// `slippage_if_nefarious`. The result is the data of an anti-sandwich program instruction that
+/// takes the accounts of the Jupiter instruction.
+///
+/// # Safety
+/// `window` must point to a valid `AsWindow`, `jupiter_data` must be valid for `jupiter_len`
+/// reads, `out` must be valid for `out_cap` writes and `out_len` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn as_adjust_slippage_data(
+    window: *const AsWindow,
+    slippage_if_nefarious: u16,
+    jupiter_data: *const u8,
+    jupiter_len: usize,
+    out: *mut u8,
+    out_cap: usize,
+    out_len: *mut usize,
+) -> AsStatus {
+    let window = try_status!(self::window(window));
+    let data = try_status!(input(jupiter_data, jupiter_len));
+    let jupiter_ix = Instruction { program_id: JUPITER_V6, accounts: vec![], data: data.to_vec() };
+    let ix = try_status!(adjust_slippage_at_runtime(&window, slippage_if_nefarious, jupiter_ix));
+    output(&ix.data, out, out_cap, out_len)
+}
+
+/// The 32-byte upstream program id.
+///
+/// # Safety
+/// `out` must be valid for 32 writes.
+#[no_mangle]
+pub unsafe extern "C" fn as_program_id(out: *mut u8) -> AsStatus {
+    if out.is_null() {
+        return AsStatus::NullPointer;
+    }
+    ptr::copy_nonoverlapping(PROGRAM_ID.as_ref().as_ptr(), out, 32);
+    AsStatus::Ok
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anti_sandwich_sdk::{
+        jupiter::{RouteArgs, RouteKind, RoutePlanStep, Swap},
+        ABORT_DISC, ADJUST_SLIPPAGE_DISC, REPORT_DISC,
+    };
+
+    #[test]
+    fn header_matches_exports() {
+        let crate_dir = env!("CARGO_MANIFEST_DIR");
+        let path = format!("{crate_dir}/include/anti_sandwich.h");
+        let config = cbindgen::Config::from_root_or_default(crate_dir);
+        let mut generated = Vec::new();
+        cbindgen::generate_with_config(crate_dir, config).unwrap().write(&mut generated);
+        if std::env::var_os("UPDATE_HEADER").is_some() {
+            std::fs::write(&path, &generated).unwrap();
+            return;
+        }
+        let checked_in = std::fs::read(&path).unwrap();
+        assert!(
+            checked_in == generated,
+            "{path} is out of date; rerun with UPDATE_HEADER=1 to regenerate it"
+        );
+    }
+
+    #[test]
+    fn window_round_trip() {
+        assert_eq!(AS_WINDOW_LEN, NefariousWindow::LEN);
+
+        let slots = [350_000_012u64, 350_000_020];
+        let mut window = AsWindow { window_start: 0, nefarious: [0; 6] };
+        unsafe {
+            assert_eq!(as_window_build(slots.as_ptr(), slots.len(), &mut window), AsStatus::Ok);
+        }
+        assert_eq!(NefariousWindow::from(window), slots[..].window().unwrap());
+
+        let mut packed = [0u8; AS_WINDOW_LEN];
+        let mut len = 0;
+        let mut unpacked = AsWindow { window_start: 0, nefarious: [0; 6] };
+        let mut nefarious = false;
+        unsafe {
+            assert_eq!(
+                as_window_pack(&window, packed.as_mut_ptr(), 4, &mut len),
+                AsStatus::BufferTooSmall
+            );
+            assert_eq!(len, AS_WINDOW_LEN);
+            assert_eq!(
+                as_window_pack(&window, packed.as_mut_ptr(), packed.len(), &mut len),
+                AsStatus::Ok
+            );
+            assert_eq!(as_window_unpack(packed.as_ptr(), len, &mut unpacked), AsStatus::Ok);
+            assert_eq!(
+                as_window_unpack(packed.as_ptr(), 3, &mut unpacked),
+                AsStatus::InvalidWindow
+            );
+            assert_eq!(as_window_is_nefarious(&window, 350_000_021, &mut nefarious), AsStatus::Ok);
+        }
+        assert_eq!(unpacked, window);
+        assert!(nefarious);
+
+        unsafe {
+            assert_eq!(
+                as_window_build_at(350_000_001, slots.as_ptr(), slots.len(), &mut window),
+                AsStatus::WindowMisaligned
+            );
+            assert_eq!(as_window_build(ptr::null(), 1, &mut window), AsStatus::NullPointer);
+            assert_eq!(as_window_build(ptr::null(), 0, &mut window), AsStatus::Ok);
+        }
+        assert_eq!(NefariousWindow::from(window), NefariousWindow::empty());
+    }
+
+    #[test]
+    fn instruction_data() {
+        let window = AsWindow { window_start: 350_000_000, nefarious: [1, 0, 0, 0, 0, 0] };
+        let mut out = [0u8; 64];
+        let mut len = 0;
+
+        unsafe {
+            assert_eq!(as_abort_data(&window, out.as_mut_ptr(), out.len(), &mut len), AsStatus::Ok);
+        }
+        assert_eq!(len, AS_WINDOW_DATA_LEN);
+        assert_eq!(out[0], ABORT_DISC);
+        assert_eq!(NefariousWindow::unpack(&out[1..len]), Some(window.into()));
+
+        unsafe {
+            assert_eq!(
+                as_report_data(&window, out.as_mut_ptr(), out.len(), &mut len),
+                AsStatus::Ok
+            );
+        }
+        assert_eq!(out[0], REPORT_DISC);
+
+        let route = RouteArgs {
+            kind: RouteKind::Route,
+            id: None,
+            route_plan: vec![RoutePlanStep {
+                swap: Swap { variant: 0, args: vec![] },
+                percent: 100,
+                input_index: 0,
+                output_index: 1,
+            }],
+            in_amount: Some(1_000),
+            quoted_out_amount: 990,
+            slippage_bps: 300,
+            platform_fee_bps: 0,
+        }
+        .encode();
+        let mut wrapped = vec![0u8; AS_ADJUST_PREFIX_LEN + route.len()];
+        unsafe {
+            assert_eq!(
+                as_adjust_slippage_data(
+                    &window,
+                    50,
+                    route.as_ptr(),
+                    route.len(),
+                    wrapped.as_mut_ptr(),
+                    wrapped.len(),
+                    &mut len
+                ),
+                AsStatus::Ok
+            );
+            assert_eq!(
+                as_adjust_slippage_data(
+                    &window,
+                    50,
+                    route.as_ptr(),
+                    8,
+                    wrapped.as_mut_ptr(),
+                    wrapped.len(),
+                    &mut len
+                ),
+                AsStatus::InvalidInstructionData
+            );
+        }
+        assert_eq!(wrapped[0], ADJUST_SLIPPAGE_DISC);
+        assert_eq!(&wrapped[AS_WINDOW_DATA_LEN..AS_ADJUST_PREFIX_LEN], 50u16.to_le_bytes());
+        assert_eq!(&wrapped[AS_ADJUST_PREFIX_LEN..], route);
+
+        let mut id = [0u8; 32];
+        unsafe {
+            assert_eq!(as_program_id(id.as_mut_ptr()), AsStatus::Ok);
+        }
+        assert_eq!(id, PROGRAM_ID.to_bytes());
+    }
+}
@@ -11,8 +11,20 @@ use pinocchio::{
 };
 
 entrypoint!(process_instruction);
-// not deployed to mainnet!
-pinocchio_pubkey::declare_id!("BfXm7pxBsqF5BpZqKSeNLzBUHXbnvase19ge2XHofhb3");
+
+/// Program ID checked in `process_instruction`. Defaults to the upstream key and can be set at
+/// build time for other deployments:
+/// `ANTI_SANDWICH_PROGRAM_ID=<base58> cargo build-sbf --manifest-path program/Cargo.toml`
+pub const ID: Pubkey = pinocchio_pubkey::from_str(match option_env!("ANTI_SANDWICH_PROGRAM_ID") {
+    Some(id) => id,
+    // not deployed to mainnet!
+    None => "BfXm7pxBsqF5BpZqKSeNLzBUHXbnvase19ge2XHofhb3",
+});
+
+#[inline(always)]
+pub const fn id() -> Pubkey {
+    ID
+}
 
 const ABORT_IF_NEFARIOUS_DISC: u8 = 1;
 const ADJUST_SLIPPAGE_VIA_JUPITER_DISC: u8 = 2;
@@ -0,0 +1,127 @@
+from typing import Optional, final
+
+PROGRAM_ID: str
+JUPITER_V6: str
+ABORT_DISC: int
+ADJUST_SLIPPAGE_DISC: int
+REPORT_DISC: int
+NEFARIOUS_LEADER: int
+
+class AntiSandwichError(ValueError): ...
+
+@final
+class NefariousWindow:
+    """`NefariousWindow` of the common crate."""
+
+    def __init__(self, window_start: int, nefarious: bytes) -> None: ...
+    @staticmethod
+    def empty() -> NefariousWindow: ...
+    @staticmethod
+    def build(slots: list[int]) -> NefariousWindow:
+        """Window starting at the lowest of `slots`."""
+    @staticmethod
+    def build_at(window_start: int, slots: list[int]) -> NefariousWindow:
+        """Window starting at the leader-aligned `window_start`."""
+    @staticmethod
+    def unpack(data: bytes) -> NefariousWindow: ...
+    def pack(self) -> bytes: ...
+    @property
+    def window_start(self) -> int: ...
+    @property
+    def nefarious(self) -> bytes: ...
+    def is_nefarious(self, slot: int) -> bool: ...
+    def land_range(self) -> range:
+        """The 192 slots the window covers. Raises `AntiSandwichError` if they don't fit a
+        signed 64-bit `range`."""
+    def next_safe_run(self, slot: int, length: int) -> Optional[int]: ...
+
+@final
+class AccountMeta:
+    def __init__(self, pubkey: str, is_signer: bool = False, is_writable: bool = False) -> None: ...
+    @property
+    def pubkey(self) -> str: ...
+    @property
+    def is_signer(self) -> bool: ...
+    @property
+    def is_writable(self) -> bool: ...
+
+@final
+class Instruction:
+    def __init__(self, program_id: str, accounts: list[AccountMeta], data: bytes) -> None: ...
+    @property
+    def program_id(self) -> str: ...
+    @property
+    def accounts(self) -> list[AccountMeta]: ...
+    @property
+    def data(self) -> bytes: ...
+
+def abort_if_nefarious(window: NefariousWindow, program_id: Optional[str] = None) -> Instruction:
+    """Guard that aborts the transaction on a nefarious leader."""
+
+def report_if_nefarious(window: NefariousWindow, program_id: Optional[str] = None) -> Instruction:
+    """Instruction reporting whether the current leader is nefarious through return data."""
+
+def adjust_slippage_at_runtime(
+    window: NefariousWindow,
+    slippage_if_nefarious: int,
+    jupiter_ix: Instruction,
+    program_id: Optional[str] = None,
+) -> Instruction:
+    """Wrap a Jupiter v6 route instruction so its slippage is lowered on a nefarious leader."""
+
+@final
+class RoutePlanStep:
+    """One step of a route plan. `args` are the borsh-encoded fields of the `Swap` variant."""
+
+    def __init__(
+        self, variant: int, args: bytes, percent: int, input_index: int, output_index: int
+    ) -> None: ...
+    @property
+    def variant(self) -> int: ...
+    @property
+    def args(self) -> bytes: ...
+    @property
+    def percent(self) -> int: ...
+    @property
+    def input_index(self) -> int: ...
+    @property
+    def output_index(self) -> int: ...
+
+@final
+class RouteArgs:
+    """Arguments of a Jupiter v6 route instruction. Decoding and re-encoding is byte for byte."""
+
+    def __init__(
+        self,
+        kind: str,
+        route_plan: list[RoutePlanStep],
+        quoted_out_amount: int,
+        slippage_bps: int,
+        platform_fee_bps: int = 0,
+        id: Optional[int] = None,
+        in_amount: Optional[int] = None,
+    ) -> None: ...
+    @staticmethod
+    def decode(data: bytes) -> RouteArgs: ...
+    @staticmethod
+    def from_instruction(ix: Instruction) -> RouteArgs:
+        """Decode the data of `ix`, which must be a Jupiter v6 instruction."""
+    def encode(self) -> bytes: ...
+    @property
+    def kind(self) -> str:
+        """`route`, `route_with_token_ledger`, `shared_accounts_route` or
+        `shared_accounts_route_with_token_ledger`."""
+    @property
+    def id(self) -> Optional[int]: ...
+    @property
+    def route_plan(self) -> list[RoutePlanStep]: ...
+    @property
+    def in_amount(self) -> Optional[int]: ...
+    @property
+    def quoted_out_amount(self) -> int: ...
+    @property
+    def slippage_bps(self) -> int: ...
+    @slippage_bps.setter
+    def slippage_bps(self, value: int) -> None: ...
+    @property
+    def platform_fee_bps(self) -> int: ...
@@ -0,0 +1,441 @@
+//! Python bindings for window building, the instruction builders and the Jupiter route codec.
+//!
+//! Pubkeys cross the boundary as base58 strings and instruction data as `bytes`. Errors of the
+//! SDK are raised as `anti_sandwich.AntiSandwichError`, a `ValueError`.
+
+use anti_sandwich_common::NefariousWindow;
+use anti_sandwich_sdk::{
+    jupiter::{self, RouteKind},
+    provider::SLOTS_PER_LEADER,
+    AntiSandwichClient, ErrorCode, WindowSource, ABORT_DISC, ADJUST_SLIPPAGE_DISC, JUPITER_V6,
+    PROGRAM_ID, REPORT_DISC,
+};
+use pyo3::{
+    create_exception,
+    exceptions::PyValueError,
+    prelude::*,
+    types::{PyBytes, PyRange},
+};
+use solana_instruction::AccountMeta as SolanaAccountMeta;
+use solana_pubkey::Pubkey;
+
+create_exception!(anti_sandwich, AntiSandwichError, PyValueError);
+
+fn to_py_err(err: anti_sandwich_sdk::AntiSandwichError) -> PyErr {
+    AntiSandwichError::new_err(err.to_string())
+}
+
+fn parse_pubkey(s: &str) -> PyResult<Pubkey> {
+    s.parse().map_err(|err| AntiSandwichError::new_err(format!("pubkey {s}: {err}")))
+}
+
+fn client(program_id: Option<&str>) -> PyResult<AntiSandwichClient> {
+    let client = AntiSandwichClient::default();
+    Ok(match program_id {
+        Some(program_id) => client.with_program_id(parse_pubkey(program_id)?),
+        None => client,
+    })
+}
+
+/// `NefariousWindow` of the common crate.
+#[pyclass(name = "NefariousWindow", module = "anti_sandwich", eq, frozen, skip_from_py_object)]
+#[derive(Clone, PartialEq)]
+struct Window(NefariousWindow);
+
+#[pymethods]
+impl Window {
+    #[new]
+    fn new(window_start: u64, nefarious: &[u8]) -> PyResult<Self> {
+        let nefarious = nefarious
+            .try_into()
+            .map_err(|_| AntiSandwichError::new_err("nefarious must be 6 bytes"))?;
+        Ok(Self(NefariousWindow { window_start, nefarious }))
+    }
+
+    #[staticmethod]
+    fn empty() -> Self {
+        Self(NefariousWindow::empty())
+    }
+
+    /// Window starting at the lowest of `slots`.
+    #[staticmethod]
+    fn build(slots: Vec<u64>) -> PyResult<Self> {
+        slots.window().map(Self).map_err(to_py_err)
+    }
+
+    /// Window starting at the leader-aligned `window_start`.
+    #[staticmethod]
+    fn build_at(window_start: u64, slots: Vec<u64>) -> PyResult<Self> {
+        anti_sandwich_sdk::build_window_at(window_start, &slots).map(Self).map_err(to_py_err)
+    }
+
+    #[staticmethod]
+    fn unpack(data: &[u8]) -> PyResult<Self> {
+        NefariousWindow::unpack(data)
+            .map(Self)
+            .ok_or_else(|| AntiSandwichError::new_err("a packed window is 14 bytes"))
+    }
+
+    fn pack<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.0.pack_to_vec())
+    }
+
+    #[getter]
+    fn window_start(&self) -> u64 {
+        self.0.window_start
+    }
+
+    #[getter]
+    fn nefarious<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.0.nefarious)
+    }
+
+    fn is_nefarious(&self, slot: u64) -> bool {
+        self.0.is_nefarious(slot)
+    }
+
+    /// The 192 slots the window covers, [`NefariousWindow::valid_land_range`] as a `range`,
+    /// whose bounds are limited to `isize`.
+    fn land_range<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyRange>> {
+        let len = (NefariousWindow::MAX_LEADERS as u64 * SLOTS_PER_LEADER) as isize;
+        let window_start = self.0.window_start;
+        let range = isize::try_from(window_start)
+            .ok()
+            .and_then(|start| Some(start..start.checked_add(len)?))
+            .ok_or_else(|| {
+                AntiSandwichError::new_err(format!(
+                    "land range of window_start {window_start} does not fit an isize"
+                ))
+            })?;
+        PyRange::new(py, range.start, range.end)
+    }
+
+    fn next_safe_run(&self, slot: u64, length: u64) -> Option<u64> {
+        self.0.next_safe_run(slot, length)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "NefariousWindow(window_start={}, nefarious=bytes({:?}))",
+            self.0.window_start, self.0.nefarious
+        )
+    }
+}
+
+#[pyclass(module = "anti_sandwich", eq, frozen, get_all, from_py_object)]
+#[derive(Clone, PartialEq)]
+struct AccountMeta {
+    pubkey: String,
+    is_signer: bool,
+    is_writable: bool,
+}
+
+#[pymethods]
+impl AccountMeta {
+    #[new]
+    #[pyo3(signature = (pubkey, is_signer = false, is_writable = false))]
+    fn new(pubkey: &str, is_signer: bool, is_writable: bool) -> PyResult<Self> {
+        parse_pubkey(pubkey)?;
+        Ok(Self { pubkey: pubkey.to_string(), is_signer, is_writable })
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "AccountMeta(pubkey={:?}, is_signer={}, is_writable={})",
+            self.pubkey,
+            if self.is_signer { "True" } else { "False" },
+            if self.is_writable { "True" } else { "False" }
+        )
+    }
+}
+
+impl From<&SolanaAccountMeta> for AccountMeta {
+    fn from(meta: &SolanaAccountMeta) -> Self {
+        Self {
+            pubkey: meta.pubkey.to_string(),
+            is_signer: meta.is_signer,
+            is_writable: meta.is_writable,
+        }
+    }
+}
+
+#[pyclass(module = "anti_sandwich", eq, frozen, skip_from_py_object)]
+#[derive(Clone, PartialEq)]
+struct Instruction(solana_instruction::Instruction);
+
+#[pymethods]
+impl Instruction {
+    #[new]
+    fn new(program_id: &str, accounts: Vec<AccountMeta>, data: &[u8]) -> PyResult<Self> {
+        let accounts = accounts
+            .iter()
+            .map(|meta| {
+                Ok(SolanaAccountMeta {
+                    pubkey: parse_pubkey(&meta.pubkey)?,
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+            })
+            .collect::<PyResult<_>>()?;
+        Ok(Self(solana_instruction::Instruction {
+            program_id: parse_pubkey(program_id)?,
+            accounts,
+            data: data.to_vec(),
+        }))
+    }
+
+    #[getter]
+    fn program_id(&self) -> String {
+        self.0.program_id.to_string()
+    }
+
+    #[getter]
+    fn accounts(&self) -> Vec<AccountMeta> {
+        self.0.accounts.iter().map(AccountMeta::from).collect()
+    }
+
+    #[getter]
+    fn data<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.0.data)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Instruction(program_id={:?}, accounts=[{} accounts], data=[{} bytes])",
+            self.0.program_id.to_string(),
+            self.0.accounts.len(),
+            self.0.data.len()
+        )
+    }
+}
+
+/// Guard that aborts the transaction on a nefarious leader.
+#[pyfunction]
+#[pyo3(signature = (window, program_id = None))]
+fn abort_if_nefarious(window: &Window, program_id: Option<&str>) -> PyResult<Instruction> {
+    client(program_id)?.abort_if_nefarious(&window.0).map(Instruction).map_err(to_py_err)
+}
+
+/// Instruction reporting whether the current leader is nefarious through return data.
+#[pyfunction]
+#[pyo3(signature = (window, program_id = None))]
+fn report_if_nefarious(window: &Window, program_id: Option<&str>) -> PyResult<Instruction> {
+    Ok(Instruction(client(program_id)?.report_if_nefarious(window.0)))
+}
+
+/// Wrap a Jupiter v6 route instruction so its slippage is lowered on a nefarious leader.
+#[pyfunction]
+#[pyo3(signature = (window, slippage_if_nefarious, jupiter_ix, program_id = None))]
+fn adjust_slippage_at_runtime(
+    window: &Window,
+    slippage_if_nefarious: u16,
+    jupiter_ix: &Instruction,
+    program_id: Option<&str>,
+) -> PyResult<Instruction> {
+    client(program_id)?
+        .adjust_slippage_at_runtime(&window.0, slippage_if_nefarious, jupiter_ix.0.clone())
+        .map(Instruction)
+        .map_err(to_py_err)
+}
+
+fn kind_name(kind: RouteKind) -> &'static str {
+    match kind {
+        RouteKind::Route => "route",
+        RouteKind::RouteWithTokenLedger => "route_with_token_ledger",
+        RouteKind::SharedAccountsRoute => "shared_accounts_route",
+        RouteKind::SharedAccountsRouteWithTokenLedger => "shared_accounts_route_with_token_ledger",
+    }
+}
+
+fn parse_kind(name: &str) -> PyResult<RouteKind> {
+    RouteKind::ALL
+        .into_iter()
+        .find(|kind| kind_name(*kind) == name)
+        .ok_or_else(|| AntiSandwichError::new_err(format!("unknown route kind {name}")))
+}
+
+/// One step of a route plan. `args` are the borsh-encoded fields of the `Swap` variant.
+#[pyclass(module = "anti_sandwich", eq, from_py_object)]
+#[derive(Clone, PartialEq)]
+struct RoutePlanStep(jupiter::RoutePlanStep);
+
+#[pymethods]
+impl RoutePlanStep {
+    #[new]
+    #[pyo3(signature = (variant, args, percent, input_index, output_index))]
+    fn new(variant: u8, args: &[u8], percent: u8, input_index: u8, output_index: u8) -> Self {
+        Self(jupiter::RoutePlanStep {
+            swap: jupiter::Swap { variant, args: args.to_vec() },
+            percent,
+            input_index,
+            output_index,
+        })
+    }
+
+    #[getter]
+    fn variant(&self) -> u8 {
+        self.0.swap.variant
+    }
+
+    #[getter]
+    fn args<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.0.swap.args)
+    }
+
+    #[getter]
+    fn percent(&self) -> u8 {
+        self.0.percent
+    }
+
+    #[getter]
+    fn input_index(&self) -> u8 {
+        self.0.input_index
+    }
+
+    #[getter]
+    fn output_index(&self) -> u8 {
+        self.0.output_index
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "RoutePlanStep(variant={}, args=[{} bytes], percent={}, input_index={}, \
+             output_index={})",
+            self.0.swap.variant,
+            self.0.swap.args.len(),
+            self.0.percent,
+            self.0.input_index,
+            self.0.output_index
+        )
+    }
+}
+
+/// Arguments of a Jupiter v6 route instruction. Decoding and re-encoding is byte for byte.
+#[pyclass(module = "anti_sandwich", eq, skip_from_py_object)]
+#[derive(Clone, PartialEq)]
+struct RouteArgs(jupiter::RouteArgs);
+
+#[pymethods]
+impl RouteArgs {
+    #[new]
+    #[pyo3(signature = (
+        kind, route_plan, quoted_out_amount, slippage_bps, platform_fee_bps = 0, id = None,
+        in_amount = None
+    ))]
+    fn new(
+        kind: &str,
+        route_plan: Vec<RoutePlanStep>,
+        quoted_out_amount: u64,
+        slippage_bps: u16,
+        platform_fee_bps: u8,
+        id: Option<u8>,
+        in_amount: Option<u64>,
+    ) -> PyResult<Self> {
+        let kind = parse_kind(kind)?;
+        if kind.has_id() != id.is_some() {
+            return Err(AntiSandwichError::new_err(
+                "id is required for shared-accounts routes only",
+            ));
+        } else if kind.has_in_amount() != in_amount.is_some() {
+            return Err(AntiSandwichError::new_err(
+                "in_amount is required for routes without a token ledger only",
+            ));
+        }
+        Ok(Self(jupiter::RouteArgs {
+            kind,
+            id,
+            route_plan: route_plan.into_iter().map(|step| step.0).collect(),
+            in_amount,
+            quoted_out_amount,
+            slippage_bps,
+            platform_fee_bps,
+        }))
+    }
+
+    #[staticmethod]
+    fn decode(data: &[u8]) -> PyResult<Self> {
+        jupiter::RouteArgs::decode(data).map(Self).map_err(to_py_err)
+    }
+
+    /// Decode the data of `ix`, which must be a Jupiter v6 instruction.
+    #[staticmethod]
+    fn from_instruction(ix: &Instruction) -> PyResult<Self> {
+        jupiter::RouteArgs::from_instruction(&ix.0).map(Self).map_err(to_py_err)
+    }
+
+    fn encode<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.0.encode())
+    }
+
+    #[getter]
+    fn kind(&self) -> &'static str {
+        kind_name(self.0.kind)
+    }
+
+    #[getter]
+    fn id(&self) -> Option<u8> {
+        self.0.id
+    }
+
+    #[getter]
+    fn route_plan(&self) -> Vec<RoutePlanStep> {
+        self.0.route_plan.iter().cloned().map(RoutePlanStep).collect()
+    }
+
+    #[getter]
+    fn in_amount(&self) -> Option<u64> {
+        self.0.in_amount
+    }
+
+    #[getter]
+    fn quoted_out_amount(&self) -> u64 {
+        self.0.quoted_out_amount
+    }
+
+    #[getter]
+    fn slippage_bps(&self) -> u16 {
+        self.0.slippage_bps
+    }
+
+    #[setter]
+    fn set_slippage_bps(&mut self, slippage_bps: u16) {
+        self.0.slippage_bps = slippage_bps;
+    }
+
+    #[getter]
+    fn platform_fee_bps(&self) -> u8 {
+        self.0.platform_fee_bps
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "RouteArgs(kind={:?}, route_plan=[{} steps], in_amount={:?}, quoted_out_amount={}, \
+             slippage_bps={})",
+            kind_name(self.0.kind),
+            self.0.route_plan.len(),
+            self.0.in_amount,
+            self.0.quoted_out_amount,
+            self.0.slippage_bps
+        )
+    }
+}
+
+#[pymodule]
+fn anti_sandwich(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("PROGRAM_ID", PROGRAM_ID.to_string())?;
+    m.add("JUPITER_V6", JUPITER_V6.to_string())?;
+    m.add("ABORT_DISC", ABORT_DISC)?;
+    m.add("ADJUST_SLIPPAGE_DISC", ADJUST_SLIPPAGE_DISC)?;
+    m.add("REPORT_DISC", REPORT_DISC)?;
+    m.add("NEFARIOUS_LEADER", ErrorCode::NefariousLeader as u32)?;
+    m.add("AntiSandwichError", m.py().get_type::<AntiSandwichError>())?;
+    m.add_class::<Window>()?;
+    m.add_class::<AccountMeta>()?;
+    m.add_class::<Instruction>()?;
+    m.add_class::<RoutePlanStep>()?;
+    m.add_class::<RouteArgs>()?;
+    m.add_function(wrap_pyfunction!(abort_if_nefarious, m)?)?;
+    m.add_function(wrap_pyfunction!(report_if_nefarious, m)?)?;
+    m.add_function(wrap_pyfunction!(adjust_slippage_at_runtime, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,122 @@
+import unittest
+
+import anti_sandwich
+from anti_sandwich import (
+    AccountMeta,
+    AntiSandwichError,
+    Instruction,
+    NefariousWindow,
+    RouteArgs,
+    RoutePlanStep,
+)
+
+# shared-accounts route with four steps, from the SDK's codec tests
+SAMPLE_ROUTE = bytes(
+    [
+        193, 32, 155, 51, 65, 214, 156, 129, 1, 4, 0, 0, 0, 25, 14, 0, 2, 25, 86, 0, 2, 61, 0, 42,
+        2, 4, 58, 0, 58, 2, 4, 0, 200, 23, 168, 4, 0, 0, 0, 125, 167, 130, 193, 0, 0, 0, 0, 16, 39,
+        0,
+    ]
+)
+USER = "5ZiE3vAkrdXBgyFL7KqG3RoEGBws4CjRcXVbABDLZTgx"
+
+
+class WindowTest(unittest.TestCase):
+    def test_build_and_query(self):
+        window = NefariousWindow.build([350_000_012, 350_000_020])
+        self.assertEqual(window.window_start, 350_000_012)
+        self.assertEqual(window.nefarious, bytes([0b101, 0, 0, 0, 0, 0]))
+        self.assertTrue(window.is_nefarious(350_000_023))
+        self.assertFalse(window.is_nefarious(350_000_024))
+        self.assertEqual(window.land_range(), range(350_000_012, 350_000_204))
+        with self.assertRaises(AntiSandwichError):
+            NefariousWindow(2**63, bytes(6)).land_range()
+        self.assertEqual(window.next_safe_run(350_000_012, 8), 350_000_024)
+
+        at = NefariousWindow.build_at(350_000_000, [350_000_012])
+        self.assertEqual(at.nefarious, bytes([0b1000, 0, 0, 0, 0, 0]))
+        self.assertEqual(NefariousWindow.build([]), NefariousWindow.empty())
+
+    def test_pack_round_trip(self):
+        window = NefariousWindow(350_000_000, bytes([1, 0, 0, 0, 0, 0x80]))
+        packed = window.pack()
+        self.assertEqual(len(packed), 14)
+        self.assertEqual(packed[:8], (350_000_000).to_bytes(8, "little"))
+        self.assertEqual(NefariousWindow.unpack(packed), window)
+
+    def test_errors(self):
+        with self.assertRaisesRegex(AntiSandwichError, "outside the 192-slot window"):
+            NefariousWindow.build([350_000_000, 350_000_192])
+        with self.assertRaisesRegex(AntiSandwichError, "not aligned"):
+            NefariousWindow.build_at(350_000_001, [])
+        with self.assertRaises(ValueError):
+            NefariousWindow.unpack(b"\x00" * 13)
+        with self.assertRaises(ValueError):
+            NefariousWindow(0, b"\x00")
+
+
+class InstructionTest(unittest.TestCase):
+    window = NefariousWindow(350_000_000, bytes([1, 0, 0, 0, 0, 0]))
+
+    def test_guards(self):
+        abort = anti_sandwich.abort_if_nefarious(self.window)
+        self.assertEqual(abort.program_id, anti_sandwich.PROGRAM_ID)
+        self.assertEqual(abort.accounts, [])
+        self.assertEqual(abort.data, bytes([anti_sandwich.ABORT_DISC]) + self.window.pack())
+
+        report = anti_sandwich.report_if_nefarious(self.window, program_id=USER)
+        self.assertEqual(report.program_id, USER)
+        self.assertEqual(report.data[0], anti_sandwich.REPORT_DISC)
+
+    def test_adjust_slippage(self):
+        accounts = [AccountMeta(USER, is_signer=True, is_writable=True)]
+        jupiter_ix = Instruction(anti_sandwich.JUPITER_V6, accounts, SAMPLE_ROUTE)
+        ix = anti_sandwich.adjust_slippage_at_runtime(self.window, 50, jupiter_ix)
+
+        self.assertEqual(ix.program_id, anti_sandwich.PROGRAM_ID)
+        self.assertEqual(ix.accounts, accounts)
+        self.assertEqual(ix.data[0], anti_sandwich.ADJUST_SLIPPAGE_DISC)
+        self.assertEqual(ix.data[1:15], self.window.pack())
+        self.assertEqual(ix.data[15:17], (50).to_bytes(2, "little"))
+        self.assertEqual(ix.data[17:], SAMPLE_ROUTE)
+
+        not_jupiter = Instruction(USER, accounts, SAMPLE_ROUTE)
+        with self.assertRaisesRegex(AntiSandwichError, "unsupported target instruction"):
+            anti_sandwich.adjust_slippage_at_runtime(self.window, 50, not_jupiter)
+        with self.assertRaises(AntiSandwichError):
+            AccountMeta("not a pubkey")
+
+
+class RouteTest(unittest.TestCase):
+    def test_decode_sample(self):
+        args = RouteArgs.decode(SAMPLE_ROUTE)
+        self.assertEqual(args.kind, "shared_accounts_route")
+        self.assertEqual(args.id, 1)
+        self.assertEqual(args.in_amount, 20_000_000_000)
+        self.assertEqual(args.quoted_out_amount, 3_246_565_245)
+        self.assertEqual(args.slippage_bps, 10_000)
+        self.assertEqual([step.variant for step in args.route_plan], [25, 25, 61, 58])
+        self.assertEqual(args.route_plan[2].args, b"\x00")
+        self.assertEqual(args.encode(), SAMPLE_ROUTE)
+
+        args.slippage_bps = 50
+        self.assertEqual(RouteArgs.decode(args.encode()).slippage_bps, 50)
+
+    def test_build(self):
+        step = RoutePlanStep(47, bytes([1, 0]), 100, 0, 1)
+        args = RouteArgs("route", [step], quoted_out_amount=990, slippage_bps=50, in_amount=1_000)
+        decoded = RouteArgs.decode(args.encode())
+        self.assertEqual(decoded, args)
+        self.assertEqual(decoded.route_plan, [step])
+
+        ix = Instruction(anti_sandwich.JUPITER_V6, [], args.encode())
+        self.assertEqual(RouteArgs.from_instruction(ix), args)
+
+        with self.assertRaisesRegex(AntiSandwichError, "in_amount"):
+            RouteArgs("route", [step], quoted_out_amount=990, slippage_bps=50)
+        with self.assertRaisesRegex(AntiSandwichError, "unknown route kind"):
+            RouteArgs("swap", [step], quoted_out_amount=990, slippage_bps=50, in_amount=1)
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,272 @@
+//! Jito bundles with an anti-sandwich guard.
+//!
+//! Bundles are still executed by the current leader, so a sandwiching leader can front-run them
+//! like any other transaction. [`BundleBuilder`] puts an `abort_if_nefarious` guard first in the
+//! bundle: bundles are atomic, so on a flagged leader the guard reverts every transaction of it,
+//! tip included.
+
+use crate::{
+    compute_budget::{
+        compute_unit_limit, processor_overhead, set_compute_unit_limit, MAX_COMPUTE_UNIT_LIMIT,
+    },
+    swap::ProtectedSwap,
+    AntiSandwichClient, AntiSandwichError, Result, WindowSource, ABORT_DISC,
+};
+use base64::{prelude::BASE64_STANDARD, Engine};
+use solana_hash::Hash;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_message::{v0, AddressLookupTableAccount, VersionedMessage};
+use solana_pubkey::{pubkey, Pubkey};
+use solana_signer::Signer;
+use solana_transaction::versioned::VersionedTransaction;
+
+/// Maximum number of transactions in a bundle.
+pub const MAX_BUNDLE_LEN: usize = 5;
+
+const SYSTEM_PROGRAM: Pubkey = pubkey!("11111111111111111111111111111111");
+/// `SystemInstruction::Transfer { lamports: u64 }`
+const SYSTEM_TRANSFER: u32 = 2;
+
+/// `SystemInstruction::Transfer` of `lamports` from `from` to `to`.
+pub fn transfer(from: &Pubkey, to: &Pubkey, lamports: u64) -> Instruction {
+    let mut data = SYSTEM_TRANSFER.to_le_bytes().to_vec();
+    data.extend_from_slice(&lamports.to_le_bytes());
+
+    Instruction {
+        program_id: SYSTEM_PROGRAM,
+        accounts: vec![AccountMeta::new(*from, true), AccountMeta::new(*to, false)],
+        data,
+    }
+}
+
+#[derive(Clone, Debug)]
+struct BundleTransaction {
+    instructions: Vec<Instruction>,
+    address_lookup_tables: Vec<AddressLookupTableAccount>,
+}
+
+/// Builds the transactions of a bundle: the caller's transactions in order, the guard in front of
+/// the first one and the tip transfer at the end of the last one.
+#[derive(Clone, Debug)]
+pub struct BundleBuilder {
+    client: AntiSandwichClient,
+    payer: Pubkey,
+    tip_account: Pubkey,
+    tip_lamports: u64,
+    transactions: Vec<BundleTransaction>,
+}
+
+impl BundleBuilder {
+    /// `payer` pays the fees of every transaction and the tip to `tip_account`, one of the tip
+    /// accounts returned by the block engine's `getTipAccounts`.
+    pub fn new(payer: Pubkey, tip_account: Pubkey, tip_lamports: u64) -> Self {
+        Self {
+            client: AntiSandwichClient::default(),
+            payer,
+            tip_account,
+            tip_lamports,
+            transactions: vec![],
+        }
+    }
+
+    /// Guard with another deployment than the upstream program.
+    pub fn with_client(mut self, client: AntiSandwichClient) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Append a transaction. `address_lookup_tables` are the tables its v0 message may load
+    /// accounts from.
+    pub fn transaction(
+        mut self,
+        instructions: Vec<Instruction>,
+        address_lookup_tables: Vec<AddressLookupTableAccount>,
+    ) -> Self {
+        self.transactions.push(BundleTransaction { instructions, address_lookup_tables });
+        self
+    }
+
+    /// Append the transaction of a protected swap. `address_lookup_tables` are the fetched
+    /// accounts of `swap.address_lookup_table_addresses`.
+    pub fn protected_swap(
+        self,
+        swap: ProtectedSwap,
+        address_lookup_tables: Vec<AddressLookupTableAccount>,
+    ) -> Self {
+        self.transaction(swap.instructions, address_lookup_tables)
+    }
+
+    /// Unsigned v0 messages of the bundle, each checked against the packet size limit.
+    pub fn build<W: WindowSource + ?Sized>(
+        &self,
+        source: &W,
+        recent_blockhash: Hash,
+    ) -> Result<Vec<VersionedMessage>> {
+        if self.transactions.is_empty() {
+            return Err(AntiSandwichError::EmptyInput("bundle"));
+        } else if self.transactions.len() > MAX_BUNDLE_LEN {
+            return Err(AntiSandwichError::InvalidConfig(format!(
+                "bundle has {} transactions, at most {MAX_BUNDLE_LEN} are allowed",
+                self.transactions.len()
+            )));
+        }
+
+        let mut transactions = self.transactions.clone();
+
+        let first = &mut transactions[0].instructions;
+        first.insert(0, self.client.abort_if_nefarious(source)?);
+        if let Some((i, units)) = compute_unit_limit(first) {
+            let overhead = processor_overhead(ABORT_DISC, 0).unwrap_or_default();
+            first[i] =
+                set_compute_unit_limit(units.saturating_add(overhead).min(MAX_COMPUTE_UNIT_LIMIT));
+        }
+
+        let last = transactions.len() - 1;
+        transactions[last].instructions.push(transfer(
+            &self.payer,
+            &self.tip_account,
+            self.tip_lamports,
+        ));
+
+        transactions
+            .iter()
+            .enumerate()
+            .map(|(i, tx)| {
+                let message = v0::Message::try_compile(
+                    &self.payer,
+                    &tx.instructions,
+                    &tx.address_lookup_tables,
+                    recent_blockhash,
+                )
+                .map_err(|err| {
+                    AntiSandwichError::InvalidConfig(format!("bundle transaction {i}: {err}"))
+                })?;
+                let message = VersionedMessage::V0(message);
+                self.client.check_transaction_size(&message)?;
+                Ok(message)
+            })
+            .collect()
+    }
+
+    /// Build and sign the bundle. Each transaction is signed by the `signers` it requires.
+    pub fn sign<W: WindowSource + ?Sized>(
+        &self,
+        source: &W,
+        recent_blockhash: Hash,
+        signers: &[&dyn Signer],
+    ) -> Result<Bundle> {
+        let transactions = self
+            .build(source, recent_blockhash)?
+            .into_iter()
+            .map(|message| {
+                let required = &message.static_account_keys()
+                    [..message.header().num_required_signatures as usize];
+                let signers: Vec<_> = signers
+                    .iter()
+                    .copied()
+                    .filter(|signer| required.contains(&signer.pubkey()))
+                    .collect();
+                VersionedTransaction::try_new(message, &signers[..])
+                    .map_err(|err| AntiSandwichError::Signing(err.to_string()))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Bundle { transactions })
+    }
+}
+
+/// Signed transactions of a bundle, in execution order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bundle {
+    pub transactions: Vec<VersionedTransaction>,
+}
+
+impl Bundle {
+    fn serialized(&self) -> impl Iterator<Item = Vec<u8>> + '_ {
+        self.transactions
+            .iter()
+            .map(|tx| bincode::serialize(tx).expect("transactions always serialize"))
+    }
+
+    /// Transactions encoded for `sendBundle` with the default base58 encoding.
+    pub fn to_base58(&self) -> Vec<String> {
+        self.serialized().map(|tx| bs58::encode(tx).into_string()).collect()
+    }
+
+    /// Transactions encoded for `sendBundle` with `"encoding": "base64"`.
+    pub fn to_base64(&self) -> Vec<String> {
+        self.serialized().map(|tx| BASE64_STANDARD.encode(tx)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        jupiter::tests::SAMPLE_ROUTE,
+        swap::{protect_swap, Protection, SwapInstructions},
+        JUPITER_V6, PROGRAM_ID,
+    };
+    use anti_sandwich_common::NefariousWindow;
+    use solana_keypair::Keypair;
+
+    #[test]
+    fn guards_first_and_tips_last() {
+        let user = Keypair::new();
+        let tip_account = Pubkey::new_unique();
+        let route =
+            Instruction { program_id: JUPITER_V6, accounts: vec![], data: SAMPLE_ROUTE.to_vec() };
+        let swap = protect_swap(
+            &NefariousWindow::empty(),
+            SwapInstructions::new(route),
+            Protection::AdjustSlippage { slippage_if_nefarious: 50 },
+        )
+        .unwrap();
+        let memo = Instruction { program_id: Pubkey::new_unique(), accounts: vec![], data: vec![] };
+
+        let window = NefariousWindow { window_start: 350_000_000, nefarious: [1, 0, 0, 0, 0, 0] };
+        let bundle = BundleBuilder::new(user.pubkey(), tip_account, 10_000)
+            .protected_swap(swap, vec![])
+            .transaction(vec![memo], vec![])
+            .sign(&window, Hash::new_unique(), &[&user])
+            .unwrap();
+        assert_eq!(bundle.transactions.len(), 2);
+
+        let first = &bundle.transactions[0].message;
+        let guard = &first.instructions()[0];
+        assert_eq!(first.static_account_keys()[guard.program_id_index as usize], PROGRAM_ID);
+        assert_eq!(guard.data[0], ABORT_DISC);
+        assert_eq!(NefariousWindow::unpack(&guard.data[1..]), Some(window));
+
+        let last = &bundle.transactions[1].message;
+        let tip = last.instructions().last().unwrap();
+        assert_eq!(last.static_account_keys()[tip.program_id_index as usize], SYSTEM_PROGRAM);
+        assert_eq!(last.static_account_keys()[tip.accounts[1] as usize], tip_account);
+        assert_eq!(tip.data[4..], 10_000u64.to_le_bytes());
+
+        for tx in &bundle.transactions {
+            assert_eq!(tx.signatures, [user.sign_message(&tx.message.serialize())]);
+        }
+        let encoded = bundle.to_base64();
+        let decoded: VersionedTransaction =
+            bincode::deserialize(&BASE64_STANDARD.decode(&encoded[0]).unwrap()).unwrap();
+        assert_eq!(decoded, bundle.transactions[0]);
+        assert_eq!(bs58::decode(&bundle.to_base58()[1]).into_vec().unwrap(), {
+            bincode::serialize(&bundle.transactions[1]).unwrap()
+        });
+
+        let too_many = (0..6)
+            .fold(BundleBuilder::new(user.pubkey(), tip_account, 0), |builder, _| {
+                builder.transaction(vec![], vec![])
+            });
+        assert!(too_many.build(&window, Hash::default()).is_err());
+
+        // the guard's overhead never raises the limit beyond what the runtime accepts
+        let maxed = BundleBuilder::new(user.pubkey(), tip_account, 0)
+            .transaction(vec![set_compute_unit_limit(MAX_COMPUTE_UNIT_LIMIT)], vec![])
+            .build(&window, Hash::default())
+            .unwrap();
+        let limit = &maxed[0].instructions()[1];
+        assert_eq!(limit.data, set_compute_unit_limit(MAX_COMPUTE_UNIT_LIMIT).data);
+    }
+}
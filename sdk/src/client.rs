@@ -0,0 +1,247 @@
+//! Program ID and cluster configuration shared by the instruction builders.
+//!
+//! The free functions at the crate root (`abort_if_nefarious`, ...) use the upstream
+//! [`PROGRAM_ID`]. Deployments under a different address go through an [`AntiSandwichClient`]
+//! instead, which passes its program ID to every builder.
+
+#[cfg(feature = "rpc")]
+use crate::provider::RpcProvider;
+use crate::{
+    check_jupiter_route, AntiSandwichError, ReportResult, Result, WindowSource, ABORT_DISC,
+    ADJUST_SLIPPAGE_DISC, PACKET_DATA_SIZE, PROGRAM_ID, REPORT_DISC,
+};
+use anti_sandwich_common::NefariousWindow;
+use base64::{prelude::BASE64_STANDARD, Engine};
+use solana_instruction::Instruction;
+use solana_pubkey::Pubkey;
+use std::{env, fmt, str::FromStr};
+
+/// Environment variable read by [`AntiSandwichClient::from_env`] (and by the program build) to
+/// override the program ID.
+pub const PROGRAM_ID_ENV: &str = "ANTI_SANDWICH_PROGRAM_ID";
+/// Environment variable read by [`AntiSandwichClient::from_env`] to select the cluster.
+pub const CLUSTER_ENV: &str = "ANTI_SANDWICH_CLUSTER";
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Cluster {
+    MainnetBeta,
+    Devnet,
+    Testnet,
+    #[default]
+    Localnet,
+}
+
+impl Cluster {
+    /// Public JSON-RPC endpoint of the cluster (rate limited, not meant for production).
+    pub fn rpc_url(self) -> &'static str {
+        match self {
+            Cluster::MainnetBeta => "https://api.mainnet-beta.solana.com",
+            Cluster::Devnet => "https://api.devnet.solana.com",
+            Cluster::Testnet => "https://api.testnet.solana.com",
+            Cluster::Localnet => "http://127.0.0.1:8899",
+        }
+    }
+}
+
+impl FromStr for Cluster {
+    type Err = AntiSandwichError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "mainnet-beta" | "mainnet" => Ok(Cluster::MainnetBeta),
+            "devnet" => Ok(Cluster::Devnet),
+            "testnet" => Ok(Cluster::Testnet),
+            "localnet" | "localhost" => Ok(Cluster::Localnet),
+            other => Err(AntiSandwichError::InvalidConfig(format!("unknown cluster {other:?}"))),
+        }
+    }
+}
+
+impl fmt::Display for Cluster {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Cluster::MainnetBeta => "mainnet-beta",
+            Cluster::Devnet => "devnet",
+            Cluster::Testnet => "testnet",
+            Cluster::Localnet => "localnet",
+        })
+    }
+}
+
+/// Builds anti-sandwich instructions for one deployment of the program.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AntiSandwichClient {
+    pub program_id: Pubkey,
+    pub cluster: Cluster,
+}
+
+impl Default for AntiSandwichClient {
+    fn default() -> Self {
+        Self::localnet()
+    }
+}
+
+impl AntiSandwichClient {
+    pub const fn new(program_id: Pubkey, cluster: Cluster) -> Self {
+        Self { program_id, cluster }
+    }
+
+    /// Client for your own deployment on mainnet-beta. The upstream [`PROGRAM_ID`] is not
+    /// deployed there.
+    pub const fn mainnet_beta(program_id: Pubkey) -> Self {
+        Self::new(program_id, Cluster::MainnetBeta)
+    }
+
+    pub const fn devnet() -> Self {
+        Self::new(PROGRAM_ID, Cluster::Devnet)
+    }
+
+    pub const fn testnet() -> Self {
+        Self::new(PROGRAM_ID, Cluster::Testnet)
+    }
+
+    /// Local validator or LiteSVM with the program loaded under the upstream ID.
+    pub const fn localnet() -> Self {
+        Self::new(PROGRAM_ID, Cluster::Localnet)
+    }
+
+    pub const fn with_program_id(mut self, program_id: Pubkey) -> Self {
+        self.program_id = program_id;
+        self
+    }
+
+    /// Preset selected by `ANTI_SANDWICH_CLUSTER` (default `localnet`), with the program ID
+    /// overridden by `ANTI_SANDWICH_PROGRAM_ID` if set. The program ID is required on
+    /// mainnet-beta, where the upstream program is not deployed.
+    pub fn from_env() -> Result<Self> {
+        let cluster = match env::var(CLUSTER_ENV) {
+            Ok(cluster) => cluster.parse()?,
+            Err(_) => Cluster::default(),
+        };
+        let program_id = match env::var(PROGRAM_ID_ENV) {
+            Ok(id) => Pubkey::from_str(&id).map_err(|err| {
+                AntiSandwichError::InvalidConfig(format!("{PROGRAM_ID_ENV}={id:?}: {err}"))
+            })?,
+            Err(_) if cluster == Cluster::MainnetBeta => {
+                return Err(AntiSandwichError::InvalidConfig(format!(
+                    "{PROGRAM_ID_ENV} is required on {cluster}"
+                )))
+            }
+            Err(_) => PROGRAM_ID,
+        };
+        Ok(Self::new(program_id, cluster))
+    }
+
+    /// JSON-RPC provider for the cluster's public endpoint.
+    #[cfg(feature = "rpc")]
+    pub fn rpc_provider(&self) -> RpcProvider {
+        RpcProvider::new(self.cluster.rpc_url())
+    }
+
+    fn window_ix(&self, disc: u8, window: &NefariousWindow) -> Instruction {
+        Instruction {
+            program_id: self.program_id,
+            accounts: vec![],
+            data: window_data(disc, window),
+        }
+    }
+
+    pub fn abort_if_nefarious<W: WindowSource + ?Sized>(&self, source: &W) -> Result<Instruction> {
+        Ok(self.window_ix(ABORT_DISC, &source.window()?))
+    }
+
+    pub fn report_if_nefarious(&self, window: NefariousWindow) -> Instruction {
+        self.window_ix(REPORT_DISC, &window)
+    }
+
+    pub fn adjust_slippage_at_runtime<W: WindowSource + ?Sized>(
+        &self,
+        source: &W,
+        slippage_if_nefarious: u16,
+        jupiter_ix: Instruction,
+    ) -> Result<Instruction> {
+        check_jupiter_route(&jupiter_ix.program_id, &jupiter_ix.data)?;
+        let data =
+            adjust_slippage_data(&source.window()?, slippage_if_nefarious, &jupiter_ix.data)?;
+
+        Ok(Instruction { program_id: self.program_id, accounts: jupiter_ix.accounts, data })
+    }
+
+    /// Decode return data set by `program_id`. `None` if the data was not set by this
+    /// deployment (e.g. a later instruction overwrote it) or is malformed.
+    pub fn parse_report(&self, program_id: &Pubkey, data: &[u8]) -> Option<ReportResult> {
+        if *program_id != self.program_id {
+            return None;
+        }
+        ReportResult::from_return_data(data)
+    }
+
+    /// Decode the `returnData` of a `simulateTransaction` response, where `program_id` is
+    /// base58 and `data` is base64 encoded.
+    pub fn parse_simulated_report(&self, program_id: &str, data: &str) -> Option<ReportResult> {
+        let program_id = program_id.parse().ok()?;
+        let data = BASE64_STANDARD.decode(data).ok()?;
+        self.parse_report(&program_id, &data)
+    }
+}
+
+/// Data of the abort and report instructions, which take no accounts.
+pub(crate) fn window_data(disc: u8, window: &NefariousWindow) -> Vec<u8> {
+    let mut data = Vec::with_capacity(1 + NefariousWindow::LEN);
+
+    data.push(disc);
+    data.extend_from_slice(&window.pack_to_vec());
+    data
+}
+
+/// Data of the adjust-slippage instruction forwarding the (already checked) Jupiter route data.
+pub(crate) fn adjust_slippage_data(
+    window: &NefariousWindow,
+    slippage_if_nefarious: u16,
+    jupiter_data: &[u8],
+) -> Result<Vec<u8>> {
+    let mut data = Vec::with_capacity(1 + NefariousWindow::LEN + 2 + jupiter_data.len());
+
+    data.push(ADJUST_SLIPPAGE_DISC);
+    data.extend_from_slice(&window.pack_to_vec());
+    data.extend_from_slice(&slippage_if_nefarious.to_le_bytes());
+    data.extend_from_slice(jupiter_data);
+
+    if data.len() > PACKET_DATA_SIZE {
+        return Err(AntiSandwichError::InstructionTooLarge {
+            len: data.len(),
+            max: PACKET_DATA_SIZE,
+        });
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builders_use_configured_program_id() {
+        let program_id = Pubkey::new_unique();
+        let client = AntiSandwichClient::devnet().with_program_id(program_id);
+        let window = NefariousWindow::empty();
+
+        assert_eq!(client.abort_if_nefarious(&window).unwrap().program_id, program_id);
+        assert_eq!(client.report_if_nefarious(window).program_id, program_id);
+        assert_eq!(client.parse_report(&program_id, &[1]), Some(ReportResult::Nefarious));
+        assert_eq!(client.parse_report(&PROGRAM_ID, &[1]), None);
+
+        // the free functions keep using the upstream deployment
+        assert_eq!(crate::abort_if_nefarious(&window).unwrap().program_id, PROGRAM_ID);
+    }
+
+    #[test]
+    fn clusters() {
+        for cluster in [Cluster::MainnetBeta, Cluster::Devnet, Cluster::Testnet, Cluster::Localnet]
+        {
+            assert_eq!(cluster.to_string().parse::<Cluster>().unwrap(), cluster);
+        }
+        assert_eq!("mainnet".parse::<Cluster>().unwrap(), Cluster::MainnetBeta);
+        assert!("mainnet-alpha".parse::<Cluster>().is_err());
+    }
+}
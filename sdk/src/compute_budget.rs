@@ -0,0 +1,171 @@
+//! Compute budget adjustment for protected instruction lists.
+//!
+//! Wrapping a Jupiter swap adds a CPI hop and logging on top of a swap whose
+//! `SetComputeUnitLimit` was sized by Jupiter, and a guard instruction costs a few hundred CU of
+//! its own. The helpers here raise the unit limit by the overhead of every anti-sandwich
+//! instruction in the list, so protected transactions don't run out of compute units.
+
+use crate::{AntiSandwichClient, ABORT_DISC, ADJUST_SLIPPAGE_DISC, REPORT_DISC};
+use solana_instruction::Instruction;
+use solana_pubkey::{pubkey, Pubkey};
+
+pub const COMPUTE_BUDGET_PROGRAM: Pubkey = pubkey!("ComputeBudget111111111111111111111111111111");
+
+/// `ComputeBudgetInstruction::SetComputeUnitLimit(u32)`
+const SET_COMPUTE_UNIT_LIMIT: u8 = 2;
+
+/// Limit the runtime applies per instruction when a transaction doesn't set one.
+pub const DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT: u32 = 200_000;
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+/// Compute units an adjust-slippage instruction adds per account it forwards to Jupiter.
+pub const CPI_OVERHEAD_PER_ACCOUNT: u32 = 60;
+
+/// Compute units used by each processor on top of what the wrapped instruction uses, for an
+/// instruction with `accounts` accounts. `None` for unknown discriminators.
+///
+/// The values are upper bounds derived from the runtime's cost model. Every processor reads the
+/// `Clock` sysvar (100 CU plus its size per 250 bytes). The report adds `set_return_data`
+/// (100 CU) and the adjust-slippage processor adds two log lines (100 CU each) and the CPI into
+/// Jupiter (1000 CU). A CPI also costs one unit per 250 bytes of account metas, keys and account
+/// data. With the processor copying each account into a meta and a CPI account,
+/// [`CPI_OVERHEAD_PER_ACCOUNT`] covers accounts of up to about 10 KiB.
+///
+/// They are checked against the compiled program by the `measures_processor_overhead` test of
+/// the `preflight` feature, which executes each processor in LiteSVM, prints the measured units
+/// and fails if any exceeds this table:
+///
+/// ```shell
+/// cargo build-sbf --manifest-path program/Cargo.toml
+/// ANTI_SANDWICH_PROGRAM_SO=$PWD/target/deploy/anti_sandwich_program.so \
+///     cargo test -p anti-sandwich-sdk --features preflight measures_processor_overhead \
+///     -- --ignored --nocapture
+/// ```
+pub const fn processor_overhead(disc: u8, accounts: usize) -> Option<u32> {
+    match disc {
+        ABORT_DISC => Some(300),
+        ADJUST_SLIPPAGE_DISC => {
+            Some(1_500u32.saturating_add(CPI_OVERHEAD_PER_ACCOUNT.saturating_mul(accounts as u32)))
+        }
+        REPORT_DISC => Some(400),
+        _ => None,
+    }
+}
+
+pub fn set_compute_unit_limit(units: u32) -> Instruction {
+    let mut data = vec![SET_COMPUTE_UNIT_LIMIT];
+    data.extend_from_slice(&units.to_le_bytes());
+    Instruction { program_id: COMPUTE_BUDGET_PROGRAM, accounts: vec![], data }
+}
+
+/// Index and value of the `SetComputeUnitLimit` instruction in `ixs`, if any.
+pub fn compute_unit_limit(ixs: &[Instruction]) -> Option<(usize, u32)> {
+    ixs.iter().enumerate().find_map(|(i, ix)| {
+        if ix.program_id != COMPUTE_BUDGET_PROGRAM {
+            return None;
+        }
+        match ix.data.split_first() {
+            Some((&SET_COMPUTE_UNIT_LIMIT, units)) => {
+                Some((i, u32::from_le_bytes(units.try_into().ok()?)))
+            }
+            _ => None,
+        }
+    })
+}
+
+/// Adjust the compute budget of `ixs` for the upstream program. Use
+/// [`AntiSandwichClient::adjust_compute_budget`] for other deployments.
+pub fn adjust_compute_budget(ixs: &mut Vec<Instruction>) -> u32 {
+    AntiSandwichClient::default().adjust_compute_budget(ixs)
+}
+
+impl AntiSandwichClient {
+    /// Compute units the instructions of this deployment in `ixs` add to the transaction.
+    pub fn compute_overhead(&self, ixs: &[Instruction]) -> u32 {
+        ixs.iter()
+            .filter(|ix| ix.program_id == self.program_id)
+            .filter_map(|ix| processor_overhead(*ix.data.first()?, ix.accounts.len()))
+            .sum()
+    }
+
+    /// Raise the `SetComputeUnitLimit` of `ixs` by [`Self::compute_overhead`]. Without one, a
+    /// limit of the runtime default plus the overhead is inserted first. The default counts
+    /// every non-compute-budget instruction as a program instruction, which slightly overestimates
+    /// transactions calling builtins. Returns the new limit, capped at
+    /// [`MAX_COMPUTE_UNIT_LIMIT`].
+    ///
+    /// Each call adds the overhead again, so call it once, on the final instruction list.
+    /// `AntiSandwichClient::protect_swap` (`swap` feature) already does.
+    pub fn adjust_compute_budget(&self, ixs: &mut Vec<Instruction>) -> u32 {
+        let overhead = self.compute_overhead(ixs);
+
+        match compute_unit_limit(ixs) {
+            Some((i, units)) => {
+                let units = units.saturating_add(overhead).min(MAX_COMPUTE_UNIT_LIMIT);
+                ixs[i] = set_compute_unit_limit(units);
+                units
+            }
+            None => {
+                let programs = ixs.iter().filter(|ix| ix.program_id != COMPUTE_BUDGET_PROGRAM);
+                let units = (programs.count() as u32)
+                    .saturating_mul(DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT)
+                    .saturating_add(overhead)
+                    .min(MAX_COMPUTE_UNIT_LIMIT);
+                ixs.insert(0, set_compute_unit_limit(units));
+                units
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{abort_if_nefarious, report_if_nefarious};
+    use anti_sandwich_common::NefariousWindow;
+    use solana_instruction::AccountMeta;
+
+    #[test]
+    fn bumps_or_inserts_limit() {
+        let window = NefariousWindow::empty();
+        let price =
+            Instruction { program_id: COMPUTE_BUDGET_PROGRAM, accounts: vec![], data: vec![3; 9] };
+        let swap = Instruction { program_id: Pubkey::new_unique(), accounts: vec![], data: vec![] };
+
+        let mut ixs = vec![
+            price.clone(),
+            set_compute_unit_limit(100_000),
+            abort_if_nefarious(&window).unwrap(),
+            report_if_nefarious(window),
+            swap.clone(),
+        ];
+        assert_eq!(adjust_compute_budget(&mut ixs), 100_700);
+        assert_eq!(compute_unit_limit(&ixs), Some((1, 100_700)));
+        assert_eq!(ixs[0], price);
+
+        let mut ixs = vec![price, abort_if_nefarious(&window).unwrap(), swap.clone()];
+        assert_eq!(adjust_compute_budget(&mut ixs), 400_300);
+        assert_eq!(compute_unit_limit(&ixs), Some((0, 400_300)));
+        assert_eq!(ixs.len(), 4);
+
+        let mut ixs = vec![set_compute_unit_limit(MAX_COMPUTE_UNIT_LIMIT), swap];
+        assert_eq!(adjust_compute_budget(&mut ixs), MAX_COMPUTE_UNIT_LIMIT);
+
+        // forwarding to Jupiter costs more with every account of the route
+        let route = |accounts| Instruction {
+            program_id: crate::JUPITER_V6,
+            accounts: (0..accounts)
+                .map(|_| AccountMeta::new_readonly(Pubkey::new_unique(), false))
+                .collect(),
+            data: crate::jupiter::tests::SAMPLE_ROUTE.to_vec(),
+        };
+        let wrapped =
+            |accounts| crate::adjust_slippage_at_runtime(&window, 50, route(accounts)).unwrap();
+        assert_eq!(AntiSandwichClient::default().compute_overhead(&[wrapped(0)]), 1_500);
+        assert_eq!(AntiSandwichClient::default().compute_overhead(&[wrapped(40)]), 3_900);
+
+        // other deployments' instructions are not counted
+        let other = AntiSandwichClient::localnet().with_program_id(Pubkey::new_unique());
+        assert_eq!(other.compute_overhead(&[abort_if_nefarious(&window).unwrap()]), 0);
+    }
+}
@@ -0,0 +1,85 @@
+#[cfg(feature = "message")]
+use crate::{outcome::TransactionFailure, size::SizeFallback};
+use solana_pubkey::{ParsePubkeyError, Pubkey};
+#[cfg(feature = "message")]
+use solana_transaction_error::TransactionError;
+use std::time::Duration;
+use thiserror::Error;
+
+pub type Result<T, E = AntiSandwichError> = std::result::Result<T, E>;
+
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum AntiSandwichError {
+    #[error("slot {slot} is outside the 192-slot window starting at {window_start}")]
+    SlotOutOfWindow { slot: u64, window_start: u64 },
+
+    #[error("window start {window_start} is not aligned to a 4-slot leader rotation")]
+    WindowMisaligned { window_start: u64 },
+
+    #[error("{0} is empty")]
+    EmptyInput(&'static str),
+
+    #[error("instruction is {len} bytes, more than the {max} bytes that fit in a transaction")]
+    InstructionTooLarge { len: usize, max: usize },
+
+    #[error("unsupported target instruction for program {program_id}: {reason}")]
+    UnsupportedTargetInstruction { program_id: Pubkey, reason: &'static str },
+
+    #[cfg(feature = "message")]
+    #[error("transaction is {len} bytes, more than the {max} bytes of a packet")]
+    TransactionTooLarge { len: usize, max: usize, fallbacks: Vec<SizeFallback> },
+
+    #[error("message has no instruction for program {program_id}")]
+    NoTargetInstruction { program_id: Pubkey },
+
+    #[error("message already references the maximum of {max} accounts")]
+    TooManyAccounts { max: usize },
+
+    #[error("invalid instruction data: {0}")]
+    InvalidInstructionData(String),
+
+    #[error("signing failed: {0}")]
+    Signing(String),
+
+    #[error("no safe leader run of {slots} slots within {max_hold:?}")]
+    NoSafeLeaderRun { slots: u64, max_hold: Duration },
+
+    #[cfg(feature = "message")]
+    #[error("transaction failed: {err}")]
+    TransactionFailed { failure: TransactionFailure, err: TransactionError },
+
+    #[error("transaction was aborted on a nefarious leader {attempts} times")]
+    RetriesExhausted { attempts: u32 },
+
+    #[error("invalid configuration: {0}")]
+    InvalidConfig(String),
+
+    /// A leader schedule, slot or blocklist provider failed or returned unusable data.
+    #[error("provider error: {0}")]
+    Provider(String),
+
+    /// Cached provider data is older than the configured limit.
+    #[error("stale data: {0}")]
+    StaleData(String),
+
+    /// An offline signing request does not decode or violates the signing policy.
+    #[error("invalid signing request: {0}")]
+    InvalidSigningRequest(String),
+}
+
+macro_rules! provider_error_from {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for AntiSandwichError {
+                fn from(err: $ty) -> Self {
+                    AntiSandwichError::Provider(err.to_string())
+                }
+            }
+        )*
+    };
+}
+
+provider_error_from!(std::io::Error, std::num::ParseIntError, serde_json::Error, ParsePubkeyError);
+
+#[cfg(feature = "rpc")]
+provider_error_from!(ureq::Error);
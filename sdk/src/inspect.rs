@@ -0,0 +1,221 @@
+//! Decoding the anti-sandwich instructions of a landed or failed transaction.
+//!
+//! [`inspect`] finds the instructions of the program in a message, decodes them and replays the
+//! decision the program made (or would make) for the slot the transaction landed in.
+
+use crate::{
+    jupiter::{RouteArgs, RouteKind},
+    AntiSandwichClient, ReportResult, ABORT_DISC, ADJUST_SLIPPAGE_DISC, REPORT_DISC,
+};
+use anti_sandwich_common::NefariousWindow;
+use solana_message::VersionedMessage;
+
+/// Minimum Jupiter data length `process_adjust_slippage_and_forward` accepts.
+const PROGRAM_MIN_JUPITER_DATA_LEN: usize = 27;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AntiSandwichInstruction {
+    AbortIfNefarious {
+        window: NefariousWindow,
+    },
+    AdjustSlippage {
+        window: NefariousWindow,
+        slippage_if_nefarious: u16,
+        /// `slippage_bps` of the forwarded Jupiter data.
+        slippage_bps: u16,
+        /// `None` if the forwarded data passes the program's checks but not the full
+        /// [`RouteArgs`] decoding.
+        route: Option<RouteArgs>,
+    },
+    ReportIfNefarious {
+        window: NefariousWindow,
+    },
+}
+
+impl AntiSandwichInstruction {
+    /// Decode instruction data the way the program does. `None` where the program fails with
+    /// `InvalidInstructionData`.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let (disc, data) = data.split_first()?;
+        match *disc {
+            ABORT_DISC => Some(Self::AbortIfNefarious { window: NefariousWindow::unpack(data)? }),
+            ADJUST_SLIPPAGE_DISC => {
+                let window = NefariousWindow::unpack(data.get(..NefariousWindow::LEN)?)?;
+                let slippage = data.get(NefariousWindow::LEN..NefariousWindow::LEN + 2)?;
+                let jupiter_data = &data[NefariousWindow::LEN + 2..];
+                if jupiter_data.len() < PROGRAM_MIN_JUPITER_DATA_LEN {
+                    return None;
+                }
+                RouteKind::from_discriminator(&jupiter_data[..8])?;
+
+                let tail = jupiter_data.len() - 3; // [slippage:u16, fee:u8]
+                Some(Self::AdjustSlippage {
+                    window,
+                    slippage_if_nefarious: u16::from_le_bytes([slippage[0], slippage[1]]),
+                    slippage_bps: u16::from_le_bytes([jupiter_data[tail], jupiter_data[tail + 1]]),
+                    route: RouteArgs::decode(jupiter_data).ok(),
+                })
+            }
+            REPORT_DISC => Some(Self::ReportIfNefarious { window: NefariousWindow::unpack(data)? }),
+            _ => None,
+        }
+    }
+
+    pub fn window(&self) -> &NefariousWindow {
+        match self {
+            Self::AbortIfNefarious { window }
+            | Self::AdjustSlippage { window, .. }
+            | Self::ReportIfNefarious { window } => window,
+        }
+    }
+}
+
+/// What the program does for an instruction at the landed slot.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// The guard fails the transaction with custom error `100`.
+    Aborted,
+    /// The guard lets the transaction through.
+    Passed,
+    /// The Jupiter swap is forwarded with its slippage lowered.
+    SlippageAdjusted {
+        from_bps: u16,
+        to_bps: u16,
+    },
+    /// The Jupiter swap is forwarded unchanged.
+    SlippageKept {
+        bps: u16,
+    },
+    Reported(ReportResult),
+    /// The program fails with `InvalidInstructionData`.
+    InvalidInstructionData,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Inspection {
+    /// Index of the instruction in the message.
+    pub index: usize,
+    /// `None` if the data is invalid.
+    pub instruction: Option<AntiSandwichInstruction>,
+    /// Whether the landed slot is inside the window at all. Outside of it, every leader counts
+    /// as not nefarious.
+    pub in_window: bool,
+    pub verdict: Verdict,
+}
+
+/// Inspect the instructions of the upstream program. Use [`AntiSandwichClient::inspect`] for
+/// other deployments.
+pub fn inspect(message: &VersionedMessage, landed_slot: u64) -> Vec<Inspection> {
+    AntiSandwichClient::default().inspect(message, landed_slot)
+}
+
+impl AntiSandwichClient {
+    /// Decode every top-level instruction of this deployment in `message` and the verdict the
+    /// program reaches for it when the transaction lands in `landed_slot`.
+    pub fn inspect(&self, message: &VersionedMessage, landed_slot: u64) -> Vec<Inspection> {
+        let keys = message.static_account_keys();
+
+        message
+            .instructions()
+            .iter()
+            .enumerate()
+            .filter(|(_, ix)| keys.get(ix.program_id_index as usize) == Some(&self.program_id))
+            .map(|(index, ix)| {
+                let instruction = AntiSandwichInstruction::decode(&ix.data);
+                let (in_window, verdict) = match &instruction {
+                    Some(instruction) => {
+                        let window = instruction.window();
+                        let nefarious = window.is_nefarious(landed_slot);
+                        let verdict = match *instruction {
+                            AntiSandwichInstruction::AbortIfNefarious { .. } if nefarious => {
+                                Verdict::Aborted
+                            }
+                            AntiSandwichInstruction::AbortIfNefarious { .. } => Verdict::Passed,
+                            AntiSandwichInstruction::AdjustSlippage {
+                                slippage_if_nefarious,
+                                slippage_bps,
+                                ..
+                            } if nefarious => Verdict::SlippageAdjusted {
+                                from_bps: slippage_bps,
+                                to_bps: slippage_if_nefarious,
+                            },
+                            AntiSandwichInstruction::AdjustSlippage { slippage_bps, .. } => {
+                                Verdict::SlippageKept { bps: slippage_bps }
+                            }
+                            AntiSandwichInstruction::ReportIfNefarious { .. } if nefarious => {
+                                Verdict::Reported(ReportResult::Nefarious)
+                            }
+                            AntiSandwichInstruction::ReportIfNefarious { .. } => {
+                                Verdict::Reported(ReportResult::NotNefarious)
+                            }
+                        };
+                        (window.valid_land_range().contains(&landed_slot), verdict)
+                    }
+                    None => (false, Verdict::InvalidInstructionData),
+                };
+                Inspection { index, instruction, in_window, verdict }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        abort_if_nefarious, adjust_slippage_at_runtime, jupiter::tests::SAMPLE_ROUTE,
+        report_if_nefarious, JUPITER_V6, PROGRAM_ID,
+    };
+    use solana_instruction::Instruction;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+
+    #[test]
+    fn decodes_and_replays() {
+        let window = NefariousWindow { window_start: 350_000_000, nefarious: [1, 0, 0, 0, 0, 0] };
+        let route =
+            Instruction { program_id: JUPITER_V6, accounts: vec![], data: SAMPLE_ROUTE.to_vec() };
+        let invalid = Instruction { program_id: PROGRAM_ID, accounts: vec![], data: vec![9] };
+        let other =
+            Instruction { program_id: Pubkey::new_unique(), accounts: vec![], data: vec![] };
+        let payer = Pubkey::new_unique();
+        let message = VersionedMessage::Legacy(Message::new(
+            &[
+                other,
+                abort_if_nefarious(&window).unwrap(),
+                adjust_slippage_at_runtime(&window, 50, route).unwrap(),
+                report_if_nefarious(window),
+                invalid,
+            ],
+            Some(&payer),
+        ));
+
+        let flagged = inspect(&message, 350_000_003);
+        assert_eq!(flagged.iter().map(|i| i.index).collect::<Vec<_>>(), [1, 2, 3, 4]);
+        assert_eq!(
+            flagged.iter().map(|i| i.verdict).collect::<Vec<_>>(),
+            [
+                Verdict::Aborted,
+                Verdict::SlippageAdjusted { from_bps: 10_000, to_bps: 50 },
+                Verdict::Reported(ReportResult::Nefarious),
+                Verdict::InvalidInstructionData,
+            ]
+        );
+        let Some(AntiSandwichInstruction::AdjustSlippage { route: Some(route), .. }) =
+            &flagged[1].instruction
+        else {
+            panic!("route not decoded");
+        };
+        assert_eq!(route.quoted_out_amount, 3_246_565_245);
+        assert!(flagged[0].in_window);
+
+        let clean = inspect(&message, 350_000_004);
+        assert_eq!(clean[0].verdict, Verdict::Passed);
+        assert_eq!(clean[1].verdict, Verdict::SlippageKept { bps: 10_000 });
+        assert_eq!(clean[2].verdict, Verdict::Reported(ReportResult::NotNefarious));
+
+        let late = inspect(&message, 350_000_192);
+        assert!(!late[0].in_window);
+        assert_eq!(late[0].verdict, Verdict::Passed);
+    }
+}
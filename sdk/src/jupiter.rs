@@ -0,0 +1,399 @@
+//! Typed codec for the Jupiter v6 route instructions that `process_adjust_slippage_and_forward`
+//! accepts: `route`, `route_with_token_ledger`, `shared_accounts_route` and
+//! `shared_accounts_route_with_token_ledger`.
+//!
+//! The instruction data is Anchor/borsh encoded:
+//!
+//! ```text
+//! discriminator: [u8; 8]
+//! id: u8                        (shared_accounts_* only)
+//! route_plan: Vec<RoutePlanStep>
+//! in_amount: u64                (not in *_with_token_ledger)
+//! quoted_out_amount: u64
+//! slippage_bps: u16
+//! platform_fee_bps: u8
+//! ```
+
+use crate::{AntiSandwichError, Result, JUPITER_V6};
+use solana_instruction::Instruction;
+use solana_pubkey::Pubkey;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RouteKind {
+    Route,
+    RouteWithTokenLedger,
+    SharedAccountsRoute,
+    SharedAccountsRouteWithTokenLedger,
+}
+
+impl RouteKind {
+    pub const ALL: [RouteKind; 4] = [
+        RouteKind::Route,
+        RouteKind::RouteWithTokenLedger,
+        RouteKind::SharedAccountsRoute,
+        RouteKind::SharedAccountsRouteWithTokenLedger,
+    ];
+
+    pub const fn discriminator(self) -> [u8; 8] {
+        match self {
+            RouteKind::Route => [229, 23, 203, 151, 122, 227, 173, 42],
+            RouteKind::RouteWithTokenLedger => [150, 86, 71, 116, 167, 93, 14, 104],
+            RouteKind::SharedAccountsRoute => [193, 32, 155, 51, 65, 214, 156, 129],
+            RouteKind::SharedAccountsRouteWithTokenLedger => {
+                [230, 121, 143, 80, 119, 159, 106, 170]
+            }
+        }
+    }
+
+    pub fn from_discriminator(disc: &[u8]) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.discriminator() == disc)
+    }
+
+    /// Shared-accounts routes carry the program authority `id` before the route plan.
+    pub const fn has_id(self) -> bool {
+        matches!(
+            self,
+            RouteKind::SharedAccountsRoute | RouteKind::SharedAccountsRouteWithTokenLedger
+        )
+    }
+
+    /// Token-ledger routes take the in amount from the ledger instead of the data.
+    pub const fn has_in_amount(self) -> bool {
+        matches!(self, RouteKind::Route | RouteKind::SharedAccountsRoute)
+    }
+
+    /// Length of the fixed-size fields after the route plan.
+    const fn tail_len(self) -> usize {
+        if self.has_in_amount() {
+            8 + 8 + 2 + 1
+        } else {
+            8 + 2 + 1
+        }
+    }
+}
+
+/// One `Swap` enum value of a route plan step: the variant index and its borsh-encoded fields,
+/// kept verbatim so the route re-encodes byte for byte.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Swap {
+    pub variant: u8,
+    pub args: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RoutePlanStep {
+    pub swap: Swap,
+    pub percent: u8,
+    pub input_index: u8,
+    pub output_index: u8,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RouteArgs {
+    pub kind: RouteKind,
+    /// Program authority id, `Some` for shared-accounts routes only.
+    pub id: Option<u8>,
+    pub route_plan: Vec<RoutePlanStep>,
+    /// `None` for token-ledger routes.
+    pub in_amount: Option<u64>,
+    pub quoted_out_amount: u64,
+    pub slippage_bps: u16,
+    pub platform_fee_bps: u8,
+}
+
+/// Minimum data length of any route instruction (token-ledger route with an empty plan).
+pub const MIN_ROUTE_DATA_LEN: usize = 8 + 4 + 8 + 2 + 1;
+
+/// Size of the fields of `Swap` variant `variant`, starting at `data`. `None` for variants this
+/// codec does not know.
+fn swap_args_len(variant: u8, data: &[u8]) -> Option<usize> {
+    // `RemainingAccountsInfo { slices: Vec<{ accounts_type: u8, length: u8 }> }`
+    let remaining_accounts_info = |data: &[u8]| -> Option<usize> {
+        let len = u32::from_le_bytes(data.get(..4)?.try_into().ok()?) as usize;
+        Some(4 + 2 * len)
+    };
+
+    Some(match variant {
+        // a_to_b / x_to_y / side / stable / is_y / is_quote_to_base
+        8 | 12 | 15..=18 | 21 | 23 | 24 | 27 | 28 | 39 | 58 | 60 | 61 | 64 | 85 => 1,
+        29 => 16,     // Symmetry { from_token_id: u64, to_token_id: u64 }
+        33 | 41 => 4, // StakeDex* { bridge_stake_seed: u32 }
+        42 => 3, // Clone { pool_index: u8, quantity_is_input: bool, quantity_is_collateral: bool }
+        43 => 10, // SanctumS { u8, u8, u32, u32 }
+        44 | 45 => 5, // SanctumS{Add,Remove}Liquidity { u8, u32 }
+        47 => {
+            // WhirlpoolSwapV2 { a_to_b: bool, remaining_accounts_info: Option<..> }
+            match data.get(1)? {
+                0 => 2,
+                1 => 2 + remaining_accounts_info(data.get(2..)?)?,
+                _ => return None,
+            }
+        }
+        71 | 86 => 2, // Perena { in_index, out_index } / GoonFi { is_bid, blacklist_bump }
+        75 => remaining_accounts_info(data)?, // MeteoraDlmmSwapV2 { remaining_accounts_info }
+        81 | 82 => 8, // RaydiumLaunchlab{Buy,Sell} { share_fee_rate: u64 }
+        87 => 9,      // HumidiFi { swap_id: u64, is_base_to_quote: bool }
+        0..=87 => 0,
+        _ => return None,
+    })
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + n)
+            .ok_or_else(|| invalid(format!("data ends before offset {}", self.pos + n)))?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().expect("2 bytes")))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().expect("4 bytes")))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().expect("8 bytes")))
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.data[self.pos..]
+    }
+}
+
+fn invalid(reason: String) -> AntiSandwichError {
+    AntiSandwichError::InvalidInstructionData(reason)
+}
+
+/// Program id and discriminator checks shared by decoding and [`RouteArgs::check_program_data`].
+fn check_route_target(program_id: &Pubkey, data: &[u8]) -> Result<()> {
+    if *program_id != JUPITER_V6 {
+        return Err(AntiSandwichError::UnsupportedTargetInstruction {
+            program_id: *program_id,
+            reason: "not a Jupiter v6 instruction",
+        });
+    } else if data.is_empty() {
+        return Err(AntiSandwichError::EmptyInput("jupiter instruction data"));
+    } else if data.get(..8).and_then(RouteKind::from_discriminator).is_none() {
+        return Err(AntiSandwichError::UnsupportedTargetInstruction {
+            program_id: *program_id,
+            reason: "not a route instruction",
+        });
+    }
+    Ok(())
+}
+
+impl RouteArgs {
+    /// Decode Jupiter v6 route instruction data.
+    ///
+    /// Swap variants unknown to this codec are accepted in the last step of the route plan only,
+    /// where their length follows from the fixed-size tail.
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        Self::decode_plan(data)?
+            .map_err(|variant| invalid(format!("unknown swap variant {variant}")))
+    }
+
+    /// [`Self::decode`], or the unknown swap variant that keeps the rest of the route plan from
+    /// being split into steps.
+    fn decode_plan(data: &[u8]) -> Result<std::result::Result<Self, u8>> {
+        let mut reader = Reader { data, pos: 0 };
+        let kind = RouteKind::from_discriminator(reader.take(8)?)
+            .ok_or_else(|| invalid("not a Jupiter v6 route instruction".to_string()))?;
+        let id = if kind.has_id() { Some(reader.u8()?) } else { None };
+
+        let steps = reader.u32()? as usize;
+        let mut route_plan = Vec::with_capacity(steps.min(64));
+        for step in 0..steps {
+            let variant = reader.u8()?;
+            let args_len =
+                match swap_args_len(variant, reader.rest()) {
+                    Some(len) => len,
+                    None if step + 1 == steps => reader
+                        .rest()
+                        .len()
+                        .checked_sub(3 + kind.tail_len())
+                        .ok_or_else(|| invalid(format!("truncated swap variant {variant}")))?,
+                    // the step's own percent and indices and the tail are still ahead
+                    None if reader.rest().len() < 3 + kind.tail_len() => {
+                        return Err(invalid(format!("truncated swap variant {variant}")))
+                    }
+                    None => return Ok(Err(variant)),
+                };
+            let swap = Swap { variant, args: reader.take(args_len)?.to_vec() };
+            route_plan.push(RoutePlanStep {
+                swap,
+                percent: reader.u8()?,
+                input_index: reader.u8()?,
+                output_index: reader.u8()?,
+            });
+        }
+
+        let in_amount = if kind.has_in_amount() { Some(reader.u64()?) } else { None };
+        let args = RouteArgs {
+            kind,
+            id,
+            route_plan,
+            in_amount,
+            quoted_out_amount: reader.u64()?,
+            slippage_bps: reader.u16()?,
+            platform_fee_bps: reader.u8()?,
+        };
+
+        if !reader.rest().is_empty() {
+            return Err(invalid(format!("{} trailing bytes", reader.rest().len())));
+        }
+        Ok(Ok(args))
+    }
+
+    /// Decode the data of a Jupiter v6 instruction, checking the program id.
+    pub fn from_instruction(ix: &Instruction) -> Result<Self> {
+        Self::from_program_data(&ix.program_id, &ix.data)
+    }
+
+    /// Same as [`Self::from_instruction`] for an instruction that is already compiled into a
+    /// message.
+    pub fn from_program_data(program_id: &Pubkey, data: &[u8]) -> Result<Self> {
+        check_route_target(program_id, data)?;
+        Self::decode(data)
+    }
+
+    /// Check the data of a Jupiter v6 instruction like [`Self::from_program_data`], but accept
+    /// swap variants this codec does not know anywhere in the route plan. Such a plan cannot be
+    /// split into steps, so only the presence of the fixed-size tail at the end of the data is
+    /// checked, which is all `process_adjust_slippage_and_forward` reads.
+    pub fn check_program_data(program_id: &Pubkey, data: &[u8]) -> Result<()> {
+        check_route_target(program_id, data)?;
+        Self::decode_plan(data).map(|_| ())
+    }
+
+    /// Slippage of a Jupiter v6 instruction, read from the fixed-size tail where
+    /// `process_adjust_slippage_and_forward` overwrites it. The data is checked like
+    /// [`Self::check_program_data`], so plans with unknown swap variants are accepted.
+    pub fn slippage_bps_of(program_id: &Pubkey, data: &[u8]) -> Result<u16> {
+        Self::check_program_data(program_id, data)?;
+        let tail = data.len() - 3; // [slippage:u16, fee:u8]
+        Ok(u16::from_le_bytes([data[tail], data[tail + 1]]))
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(MIN_ROUTE_DATA_LEN + 1 + 8 + self.route_plan.len() * 8);
+
+        data.extend_from_slice(&self.kind.discriminator());
+        if self.kind.has_id() {
+            data.push(self.id.unwrap_or_default());
+        }
+        data.extend_from_slice(&(self.route_plan.len() as u32).to_le_bytes());
+        for step in &self.route_plan {
+            data.push(step.swap.variant);
+            data.extend_from_slice(&step.swap.args);
+            data.extend_from_slice(&[step.percent, step.input_index, step.output_index]);
+        }
+        if self.kind.has_in_amount() {
+            data.extend_from_slice(&self.in_amount.unwrap_or_default().to_le_bytes());
+        }
+        data.extend_from_slice(&self.quoted_out_amount.to_le_bytes());
+        data.extend_from_slice(&self.slippage_bps.to_le_bytes());
+        data.push(self.platform_fee_bps);
+
+        data
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// `shared_accounts_route` from `examples/adjust_slippage`: 20 SOL -> USDC through
+    /// LifinityV2 (14% / 86%), then SolFi and Obric (42% / 58%).
+    pub(crate) const SAMPLE_ROUTE: [u8; 50] = [
+        193, 32, 155, 51, 65, 214, 156, 129, 1, 4, 0, 0, 0, 25, 14, 0, 2, 25, 86, 0, 2, 61, 0, 42,
+        2, 4, 58, 0, 58, 2, 4, 0, 200, 23, 168, 4, 0, 0, 0, 125, 167, 130, 193, 0, 0, 0, 0, 16, 39,
+        0,
+    ];
+
+    #[test]
+    fn decodes_sample_route() {
+        let args = RouteArgs::decode(&SAMPLE_ROUTE).unwrap();
+        assert_eq!(args.kind, RouteKind::SharedAccountsRoute);
+        assert_eq!(args.id, Some(1));
+        assert_eq!(args.in_amount, Some(20_000_000_000));
+        assert_eq!(args.quoted_out_amount, 3_246_565_245);
+        assert_eq!(args.slippage_bps, 10_000);
+        assert_eq!(args.platform_fee_bps, 0);
+
+        let variants: Vec<_> = args.route_plan.iter().map(|s| s.swap.variant).collect();
+        assert_eq!(variants, [25, 25, 61, 58]);
+        let percents: Vec<_> = args.route_plan.iter().map(|s| s.percent).collect();
+        assert_eq!(percents, [14, 86, 42, 58]);
+        assert_eq!(args.route_plan[2].swap.args, [0]);
+
+        assert_eq!(args.encode(), SAMPLE_ROUTE);
+    }
+
+    #[test]
+    fn round_trips_every_kind() {
+        for kind in RouteKind::ALL {
+            let args = RouteArgs {
+                kind,
+                id: kind.has_id().then_some(3),
+                route_plan: vec![
+                    RoutePlanStep {
+                        swap: Swap { variant: 47, args: vec![1, 1, 2, 0, 0, 0, 0, 3, 1, 4] },
+                        percent: 100,
+                        input_index: 0,
+                        output_index: 1,
+                    },
+                    RoutePlanStep {
+                        swap: Swap { variant: 250, args: vec![9, 9, 9] },
+                        percent: 100,
+                        input_index: 1,
+                        output_index: 2,
+                    },
+                ],
+                in_amount: kind.has_in_amount().then_some(1_000),
+                quoted_out_amount: 990,
+                slippage_bps: 50,
+                platform_fee_bps: 2,
+            };
+            assert_eq!(RouteArgs::decode(&args.encode()).unwrap(), args, "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_data() {
+        assert!(RouteArgs::decode(&SAMPLE_ROUTE[..SAMPLE_ROUTE.len() - 1]).is_err());
+
+        let mut trailing = SAMPLE_ROUTE.to_vec();
+        trailing.push(0);
+        assert!(RouteArgs::decode(&trailing).is_err());
+
+        let mut unknown_first = SAMPLE_ROUTE.to_vec();
+        unknown_first[13] = 250;
+        assert!(RouteArgs::decode(&unknown_first).is_err());
+        // the forwarding program only reads the tail, so such routes are still accepted
+        assert_eq!(RouteArgs::check_program_data(&JUPITER_V6, &unknown_first), Ok(()));
+        let header = 8 + 1 + 4 + 1;
+        let truncated = &unknown_first[..header + 3 + 8 + 8 + 2];
+        assert!(matches!(
+            RouteArgs::check_program_data(&JUPITER_V6, truncated),
+            Err(AntiSandwichError::InvalidInstructionData(_))
+        ));
+
+        let mut disc = SAMPLE_ROUTE.to_vec();
+        disc[0] ^= 1;
+        assert!(RouteArgs::decode(&disc).is_err());
+    }
+}
@@ -1,10 +1,92 @@
+#[cfg(feature = "jito")]
+pub mod bundle;
+pub mod client;
+pub mod compute_budget;
+mod error;
+#[cfg(feature = "message")]
+pub mod inspect;
+pub mod jupiter;
+#[cfg(feature = "message")]
+pub mod message;
+#[cfg(feature = "offline")]
+pub mod offline;
+#[cfg(feature = "message")]
+pub mod outcome;
+#[cfg(feature = "policy")]
+pub mod policy;
+#[cfg(feature = "preflight")]
+pub mod preflight;
+pub mod provider;
+pub mod report;
+#[cfg(feature = "scheduler")]
+pub mod retry;
+#[cfg(feature = "scheduler")]
+pub mod scheduler;
+#[cfg(feature = "tokio")]
+pub mod service;
+#[cfg(feature = "message")]
+pub mod size;
+pub mod slippage;
+#[cfg(feature = "swap")]
+pub mod swap;
+#[cfg(feature = "tokio")]
+pub mod updates;
+
+pub use anti_sandwich_common::ErrorCode;
+pub use client::{AntiSandwichClient, Cluster};
+pub use error::{AntiSandwichError, Result};
+pub use report::ReportResult;
+#[cfg(feature = "solana-program")]
+pub use solana_program;
+
 use anti_sandwich_common::NefariousWindow;
-use solana_program::{instruction::Instruction, pubkey, pubkey::Pubkey};
+use jupiter::RouteArgs;
+use solana_instruction::Instruction;
+use solana_pubkey::{pubkey, Pubkey};
 
 // not deployed to mainnet!
 pub const PROGRAM_ID: Pubkey = pubkey!("BfXm7pxBsqF5BpZqKSeNLzBUHXbnvase19ge2XHofhb3");
 pub const ABORT_DISC: u8 = 1;
 pub const ADJUST_SLIPPAGE_DISC: u8 = 2;
+pub const REPORT_DISC: u8 = 3;
+
+pub const JUPITER_V6: Pubkey = pubkey!("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4");
+
+/// Maximum size of a serialized transaction (`PACKET_DATA_SIZE`).
+pub const PACKET_DATA_SIZE: usize = 1232;
+
+/// Anything that can produce the `NefariousWindow` embedded into an instruction.
+///
+/// Implemented for plain lists of nefarious leader slots (the window starts at the lowest slot),
+/// for a prebuilt `NefariousWindow`, and for [`provider::ProviderWindow`], which derives the
+/// window from a leader schedule, the current slot and a validator blocklist.
+pub trait WindowSource {
+    fn window(&self) -> Result<NefariousWindow>;
+}
+
+impl WindowSource for [u64] {
+    fn window(&self) -> Result<NefariousWindow> {
+        build_window(self)
+    }
+}
+
+impl<const N: usize> WindowSource for [u64; N] {
+    fn window(&self) -> Result<NefariousWindow> {
+        build_window(self)
+    }
+}
+
+impl WindowSource for Vec<u64> {
+    fn window(&self) -> Result<NefariousWindow> {
+        build_window(self)
+    }
+}
+
+impl WindowSource for NefariousWindow {
+    fn window(&self) -> Result<NefariousWindow> {
+        Ok(*self)
+    }
+}
 
 /// Build the 192‑slot NefariousWindow that records which 4‑slot‑leaders are nefarious.
 ///
@@ -14,50 +96,136 @@ pub const ADJUST_SLIPPAGE_DISC: u8 = 2;
 ///
 /// Returns an error if a supplied slot is outside the
 /// `[baseline_slot, baseline_slot + 191]` range.
-fn build_window(nefarious_leader_slots: &[u64]) -> eyre::Result<NefariousWindow> {
+fn build_window(nefarious_leader_slots: &[u64]) -> Result<NefariousWindow> {
     if nefarious_leader_slots.is_empty() {
         return Ok(NefariousWindow::empty());
     }
 
     let baseline_slot = *nefarious_leader_slots.iter().min().expect("cannot be empty");
+    pack_slots(baseline_slot, nefarious_leader_slots)
+}
+
+/// Same as `build_window`, but the window starts at `window_start` instead of the lowest
+/// nefarious slot. Used when the window is anchored at the current slot.
+///
+/// `window_start` must be the first slot of a leader rotation, so that every bit of the window
+/// covers exactly one leader.
+pub fn build_window_at(
+    window_start: u64,
+    nefarious_leader_slots: &[u64],
+) -> Result<NefariousWindow> {
+    if !window_start.is_multiple_of(provider::SLOTS_PER_LEADER) {
+        return Err(AntiSandwichError::WindowMisaligned { window_start });
+    }
+    pack_slots(window_start, nefarious_leader_slots)
+}
+
+fn pack_slots(window_start: u64, nefarious_leader_slots: &[u64]) -> Result<NefariousWindow> {
     let mut bits = [0u8; 6];
 
     for &slot in nefarious_leader_slots {
-        if !(baseline_slot..=baseline_slot + 191).contains(&slot) {
-            return Err(eyre::eyre!(
-                "slot {slot} is outside the 192‑slot window starting at {baseline_slot}"
-            ));
+        if !(window_start..=window_start + 191).contains(&slot) {
+            return Err(AntiSandwichError::SlotOutOfWindow { slot, window_start });
         }
 
-        let leader = ((slot - baseline_slot) / 4) as usize;
+        let leader = ((slot - window_start) / 4) as usize;
         bits[leader / 8] |= 1 << (leader % 8);
     }
 
-    Ok(NefariousWindow { window_start: baseline_slot, nefarious: bits })
+    Ok(NefariousWindow { window_start, nefarious: bits })
 }
 
-pub fn abort_if_nefarious(nefarious_leader_slots: &[u64]) -> eyre::Result<Instruction> {
-    let window = build_window(nefarious_leader_slots)?;
-    let mut data = Vec::with_capacity(1 + NefariousWindow::LEN);
-
-    data.push(ABORT_DISC);
-    data.extend_from_slice(&window.pack_to_vec());
+pub fn abort_if_nefarious<W: WindowSource + ?Sized>(source: &W) -> Result<Instruction> {
+    AntiSandwichClient::default().abort_if_nefarious(source)
+}
 
-    Ok(Instruction { program_id: PROGRAM_ID, accounts: vec![], data })
+/// Instruction that reports whether the current leader is nefarious through return data,
+/// without failing the transaction. Decode the result with [`ReportResult`].
+pub fn report_if_nefarious(window: NefariousWindow) -> Instruction {
+    AntiSandwichClient::default().report_if_nefarious(window)
 }
 
-pub fn adjust_slippage_at_runtime(
-    nefarious_leader_slots: &[u64],
+pub fn adjust_slippage_at_runtime<W: WindowSource + ?Sized>(
+    source: &W,
     slippage_if_nefarious: u16,
     jupiter_ix: Instruction,
-) -> eyre::Result<Instruction> {
-    let window = build_window(nefarious_leader_slots)?;
-    let mut data = Vec::with_capacity(1 + NefariousWindow::LEN + 2 + jupiter_ix.data.len());
+) -> Result<Instruction> {
+    AntiSandwichClient::default().adjust_slippage_at_runtime(
+        source,
+        slippage_if_nefarious,
+        jupiter_ix,
+    )
+}
+
+/// Check the Jupiter route, which also covers the sanity checks
+/// `process_adjust_slippage_and_forward` performs on-chain.
+pub(crate) fn check_jupiter_route(program_id: &Pubkey, data: &[u8]) -> Result<()> {
+    RouteArgs::check_program_data(program_id, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route_ix(data: Vec<u8>) -> Instruction {
+        Instruction { program_id: JUPITER_V6, accounts: vec![], data }
+    }
+
+    #[test]
+    fn window_errors() {
+        assert_eq!(
+            abort_if_nefarious(&[350_000_000, 350_000_192]).unwrap_err(),
+            AntiSandwichError::SlotOutOfWindow { slot: 350_000_192, window_start: 350_000_000 }
+        );
+        assert_eq!(
+            build_window_at(350_000_002, &[]).unwrap_err(),
+            AntiSandwichError::WindowMisaligned { window_start: 350_000_002 }
+        );
+        assert!(build_window_at(350_000_004, &[350_000_195]).unwrap().is_nefarious(350_000_192));
+    }
+
+    #[test]
+    fn rejects_unsupported_targets() {
+        let mut data = jupiter::tests::SAMPLE_ROUTE.to_vec();
+        assert!(adjust_slippage_at_runtime(&NefariousWindow::empty(), 50, route_ix(data.clone()))
+            .is_ok());
+
+        let not_jupiter = Instruction { program_id: PROGRAM_ID, ..route_ix(data.clone()) };
+        assert!(matches!(
+            adjust_slippage_at_runtime(&NefariousWindow::empty(), 50, not_jupiter),
+            Err(AntiSandwichError::UnsupportedTargetInstruction { .. })
+        ));
+        assert_eq!(
+            adjust_slippage_at_runtime(&NefariousWindow::empty(), 50, route_ix(vec![]))
+                .unwrap_err(),
+            AntiSandwichError::EmptyInput("jupiter instruction data")
+        );
+        data[0] ^= 1;
+        assert!(matches!(
+            adjust_slippage_at_runtime(&NefariousWindow::empty(), 50, route_ix(data.clone())),
+            Err(AntiSandwichError::UnsupportedTargetInstruction { .. })
+        ));
 
-    data.push(ADJUST_SLIPPAGE_DISC);
-    data.extend_from_slice(&window.pack_to_vec());
-    data.extend_from_slice(&slippage_if_nefarious.to_le_bytes());
-    data.extend_from_slice(&jupiter_ix.data);
+        data[0] ^= 1;
+        data.push(0);
+        assert!(matches!(
+            adjust_slippage_at_runtime(&NefariousWindow::empty(), 50, route_ix(data)),
+            Err(AntiSandwichError::InvalidInstructionData(_))
+        ));
 
-    Ok(Instruction { program_id: PROGRAM_ID, accounts: jupiter_ix.accounts, data })
+        // routes through swap variants the codec does not know are forwarded as before
+        let mut unknown = jupiter::tests::SAMPLE_ROUTE.to_vec();
+        unknown[13] = 250;
+        assert!(
+            adjust_slippage_at_runtime(&NefariousWindow::empty(), 50, route_ix(unknown)).is_ok()
+        );
+
+        let mut oversized = jupiter::RouteArgs::decode(&jupiter::tests::SAMPLE_ROUTE).unwrap();
+        oversized.route_plan.last_mut().unwrap().swap =
+            jupiter::Swap { variant: 250, args: vec![0; PACKET_DATA_SIZE] };
+        assert!(matches!(
+            adjust_slippage_at_runtime(&NefariousWindow::empty(), 50, route_ix(oversized.encode())),
+            Err(AntiSandwichError::InstructionTooLarge { .. })
+        ));
+    }
 }
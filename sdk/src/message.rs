@@ -0,0 +1,349 @@
+//! Protecting transactions that are already compiled.
+//!
+//! Wallets and relayers often receive a fully built transaction from a third party. Instead of
+//! decompiling and rebuilding it, the functions here patch its legacy or v0 message so everything
+//! but the protected instructions stays as the third party compiled it. The returned message is
+//! unsigned: the original signatures no longer match and the signers have to sign it again.
+
+use crate::{
+    check_jupiter_route,
+    client::{adjust_slippage_data, window_data},
+    AntiSandwichClient, AntiSandwichError, Result, WindowSource, ABORT_DISC, JUPITER_V6,
+};
+use solana_message::{compiled_instruction::CompiledInstruction, MessageHeader, VersionedMessage};
+use solana_pubkey::Pubkey;
+
+/// Account indices are a `u8`, static and lookup table accounts included.
+const MAX_ACCOUNTS: usize = 256;
+
+/// Rewrite every Jupiter v6 route instruction of `message` into an adjust-slippage instruction of
+/// the upstream program. Use [`AntiSandwichClient::protect_jupiter_message`] for other
+/// deployments.
+pub fn protect_jupiter_message<W: WindowSource + ?Sized>(
+    message: &VersionedMessage,
+    source: &W,
+    slippage_if_nefarious: u16,
+) -> Result<VersionedMessage> {
+    AntiSandwichClient::default().protect_jupiter_message(message, source, slippage_if_nefarious)
+}
+
+/// Where [`protect_message`] inserts the abort guard.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GuardPosition {
+    First,
+    /// Right before the first instruction of the given program.
+    BeforeProgram(Pubkey),
+    Last,
+}
+
+/// Insert an `abort_if_nefarious` guard of the upstream program into `message`. Use
+/// [`AntiSandwichClient::protect_message`] for other deployments.
+pub fn protect_message<W: WindowSource + ?Sized>(
+    message: &VersionedMessage,
+    source: &W,
+    position: GuardPosition,
+) -> Result<VersionedMessage> {
+    AntiSandwichClient::default().protect_message(message, source, position)
+}
+
+/// The parts of a legacy or v0 message that change when an instruction is rewritten.
+pub(crate) struct MessageParts<'a> {
+    pub(crate) header: &'a mut MessageHeader,
+    pub(crate) account_keys: &'a mut Vec<Pubkey>,
+    pub(crate) instructions: &'a mut Vec<CompiledInstruction>,
+    /// Number of accounts loaded from lookup tables, indexed after the static keys.
+    loaded: usize,
+}
+
+impl<'a> MessageParts<'a> {
+    pub(crate) fn new(message: &'a mut VersionedMessage) -> Self {
+        match message {
+            VersionedMessage::Legacy(message) => MessageParts {
+                header: &mut message.header,
+                account_keys: &mut message.account_keys,
+                instructions: &mut message.instructions,
+                loaded: 0,
+            },
+            VersionedMessage::V0(message) => MessageParts {
+                loaded: message
+                    .address_table_lookups
+                    .iter()
+                    .map(|lookup| lookup.writable_indexes.len() + lookup.readonly_indexes.len())
+                    .sum(),
+                header: &mut message.header,
+                account_keys: &mut message.account_keys,
+                instructions: &mut message.instructions,
+            },
+        }
+    }
+
+    pub(crate) fn program_id(&self, ix: &CompiledInstruction) -> Option<&Pubkey> {
+        self.account_keys.get(ix.program_id_index as usize)
+    }
+
+    /// Index of `program_id` among the static keys, appending it as a readonly non-signer if it
+    /// is missing.
+    ///
+    /// Programs cannot be loaded from lookup tables. Appending a static key moves every account
+    /// loaded from a lookup table up by one index, so instruction accounts pointing past the
+    /// static keys are shifted as well. `program_id` must not be in any of the message's lookup
+    /// tables, or the transaction would load it twice.
+    pub(crate) fn program_index(&mut self, program_id: &Pubkey) -> Result<u8> {
+        if let Some(index) = self.account_keys.iter().position(|key| key == program_id) {
+            return Ok(index as u8);
+        }
+
+        let index = self.account_keys.len();
+        if index + self.loaded >= MAX_ACCOUNTS {
+            return Err(AntiSandwichError::TooManyAccounts { max: MAX_ACCOUNTS });
+        }
+        for ix in self.instructions.iter_mut() {
+            for account in ix.accounts.iter_mut().filter(|account| **account as usize >= index) {
+                *account += 1;
+            }
+        }
+        self.account_keys.push(*program_id);
+        self.header.num_readonly_unsigned_accounts += 1;
+
+        Ok(index as u8)
+    }
+}
+
+impl AntiSandwichClient {
+    /// Rewrite every Jupiter v6 route instruction of `message` into an adjust-slippage
+    /// instruction: the program id index points at this deployment (added to the static keys if
+    /// needed) and the data is prefixed with the window and `slippage_if_nefarious`. Accounts,
+    /// signers and writability are left untouched.
+    ///
+    /// Fails if `message` has no Jupiter v6 instruction, or if one of them is not a route
+    /// instruction the program can forward.
+    pub fn protect_jupiter_message<W: WindowSource + ?Sized>(
+        &self,
+        message: &VersionedMessage,
+        source: &W,
+        slippage_if_nefarious: u16,
+    ) -> Result<VersionedMessage> {
+        let mut message = message.clone();
+        let mut parts = MessageParts::new(&mut message);
+
+        let mut jupiter_ixs = vec![];
+        for (i, ix) in parts.instructions.iter().enumerate() {
+            if parts.program_id(ix) == Some(&JUPITER_V6) {
+                check_jupiter_route(&JUPITER_V6, &ix.data)?;
+                jupiter_ixs.push(i);
+            }
+        }
+        if jupiter_ixs.is_empty() {
+            return Err(AntiSandwichError::NoTargetInstruction { program_id: JUPITER_V6 });
+        }
+
+        let window = source.window()?;
+        let program_index = parts.program_index(&self.program_id)?;
+        for i in jupiter_ixs {
+            let ix = &mut parts.instructions[i];
+            ix.data = adjust_slippage_data(&window, slippage_if_nefarious, &ix.data)?;
+            ix.program_id_index = program_index;
+        }
+
+        Ok(message)
+    }
+
+    /// Insert an `abort_if_nefarious` guard into `message`, so the whole transaction fails on a
+    /// nefarious leader whatever it does. The guard takes no accounts; the only key it may add is
+    /// this deployment's program id, as a readonly non-signer. All other keys keep their signer
+    /// and writable flags, and lookup table indices stay valid.
+    pub fn protect_message<W: WindowSource + ?Sized>(
+        &self,
+        message: &VersionedMessage,
+        source: &W,
+        position: GuardPosition,
+    ) -> Result<VersionedMessage> {
+        let mut message = message.clone();
+        let mut parts = MessageParts::new(&mut message);
+
+        let at = match position {
+            GuardPosition::First => 0,
+            GuardPosition::BeforeProgram(program_id) => parts
+                .instructions
+                .iter()
+                .position(|ix| parts.program_id(ix) == Some(&program_id))
+                .ok_or(AntiSandwichError::NoTargetInstruction { program_id })?,
+            GuardPosition::Last => parts.instructions.len(),
+        };
+
+        let guard = CompiledInstruction {
+            program_id_index: parts.program_index(&self.program_id)?,
+            accounts: vec![],
+            data: window_data(ABORT_DISC, &source.window()?),
+        };
+        parts.instructions.insert(at, guard);
+
+        Ok(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{jupiter::tests::SAMPLE_ROUTE, ADJUST_SLIPPAGE_DISC, PROGRAM_ID};
+    use anti_sandwich_common::NefariousWindow;
+    use solana_hash::Hash;
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_message::{v0, AddressLookupTableAccount, Message};
+
+    /// Program id and accounts of every instruction, resolved through `alt`.
+    fn resolve(
+        message: &VersionedMessage,
+        alt: &AddressLookupTableAccount,
+    ) -> Vec<(Pubkey, Vec<(Pubkey, bool)>)> {
+        let mut keys = message.static_account_keys().to_vec();
+        let lookups = message.address_table_lookups().unwrap_or_default();
+        for lookup in lookups {
+            keys.extend(lookup.writable_indexes.iter().map(|&i| alt.addresses[i as usize]));
+        }
+        for lookup in lookups {
+            keys.extend(lookup.readonly_indexes.iter().map(|&i| alt.addresses[i as usize]));
+        }
+
+        message
+            .instructions()
+            .iter()
+            .map(|ix| {
+                let accounts = ix
+                    .accounts
+                    .iter()
+                    .map(|&i| (keys[i as usize], message.is_maybe_writable(i as usize, None)))
+                    .collect();
+                (keys[ix.program_id_index as usize], accounts)
+            })
+            .collect()
+    }
+
+    fn jupiter_ix(payer: Pubkey, pool: Pubkey, mint: Pubkey) -> Instruction {
+        let accounts = vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new(pool, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new_readonly(JUPITER_V6, false),
+        ];
+        Instruction { program_id: JUPITER_V6, accounts, data: SAMPLE_ROUTE.to_vec() }
+    }
+
+    #[test]
+    fn rewrites_v0_message_with_lookup_tables() {
+        let (payer, pool, mint, other) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let alt =
+            AddressLookupTableAccount { key: Pubkey::new_unique(), addresses: vec![pool, mint] };
+        let other_ix = Instruction {
+            program_id: other,
+            accounts: vec![AccountMeta::new(pool, false)],
+            data: vec![7],
+        };
+        let message = VersionedMessage::V0(
+            v0::Message::try_compile(
+                &payer,
+                &[jupiter_ix(payer, pool, mint), other_ix],
+                std::slice::from_ref(&alt),
+                Hash::default(),
+            )
+            .unwrap(),
+        );
+
+        let window = NefariousWindow { window_start: 350_000_000, nefarious: [1, 0, 0, 0, 0, 0] };
+        let protected = protect_jupiter_message(&message, &window, 50).unwrap();
+
+        let before = resolve(&message, &alt);
+        let after = resolve(&protected, &alt);
+        assert_eq!(after[0], (PROGRAM_ID, before[0].1.clone()));
+        assert_eq!(after[1], before[1]);
+
+        let data = &protected.instructions()[0].data;
+        assert_eq!(data[0], ADJUST_SLIPPAGE_DISC);
+        assert_eq!(NefariousWindow::unpack(&data[1..15]), Some(window));
+        assert_eq!(data[15..17], 50u16.to_le_bytes());
+        assert_eq!(data[17..], SAMPLE_ROUTE);
+
+        assert_eq!(
+            protected.header().num_required_signatures,
+            message.header().num_required_signatures
+        );
+        assert_eq!(protected.address_table_lookups(), message.address_table_lookups());
+        assert_eq!(protected.recent_blockhash(), message.recent_blockhash());
+    }
+
+    #[test]
+    fn rewrites_legacy_message() {
+        let (payer, pool, mint) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let message =
+            VersionedMessage::Legacy(Message::new(&[jupiter_ix(payer, pool, mint)], Some(&payer)));
+
+        let protected = protect_jupiter_message(&message, &NefariousWindow::empty(), 50).unwrap();
+        assert_eq!(protected.static_account_keys().last(), Some(&PROGRAM_ID));
+        assert_eq!(
+            protected.header().num_readonly_unsigned_accounts,
+            message.header().num_readonly_unsigned_accounts + 1
+        );
+        assert!(protect_jupiter_message(&protected, &NefariousWindow::empty(), 50).is_err());
+    }
+
+    #[test]
+    fn inserts_guard() {
+        let (payer, pool, mint, raydium) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let alt =
+            AddressLookupTableAccount { key: Pubkey::new_unique(), addresses: vec![pool, mint] };
+        let swap = Instruction {
+            program_id: raydium,
+            accounts: vec![
+                AccountMeta::new(payer, true),
+                AccountMeta::new(pool, false),
+                AccountMeta::new_readonly(mint, false),
+            ],
+            data: vec![9],
+        };
+        let memo = Instruction { program_id: Pubkey::new_unique(), accounts: vec![], data: vec![] };
+        let message = VersionedMessage::V0(
+            v0::Message::try_compile(
+                &payer,
+                &[memo, swap],
+                std::slice::from_ref(&alt),
+                Hash::default(),
+            )
+            .unwrap(),
+        );
+        let window = NefariousWindow { window_start: 350_000_000, nefarious: [1, 0, 0, 0, 0, 0] };
+        let before = resolve(&message, &alt);
+
+        for (position, at) in [
+            (GuardPosition::First, 0),
+            (GuardPosition::BeforeProgram(raydium), 1),
+            (GuardPosition::Last, 2),
+        ] {
+            let protected = protect_message(&message, &window, position).unwrap();
+            let mut after = resolve(&protected, &alt);
+            assert_eq!(after.remove(at), (PROGRAM_ID, vec![]), "{position:?}");
+            assert_eq!(after, before, "{position:?}");
+
+            let guard = &protected.instructions()[at];
+            assert_eq!(guard.data[0], ABORT_DISC);
+            assert_eq!(NefariousWindow::unpack(&guard.data[1..]), Some(window));
+            assert!(!protected.is_maybe_writable(guard.program_id_index as usize, None));
+        }
+
+        let missing = Pubkey::new_unique();
+        assert_eq!(
+            protect_message(&message, &window, GuardPosition::BeforeProgram(missing)).unwrap_err(),
+            AntiSandwichError::NoTargetInstruction { program_id: missing }
+        );
+    }
+}
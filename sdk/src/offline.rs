@@ -0,0 +1,475 @@
+//! Signing protected transactions on an air-gapped machine.
+//!
+//! The online machine builds the protected message and wraps it in a [`SigningRequest`] together
+//! with the window it was protected with, where that window came from and the slots the
+//! transaction is expected to land in. The request travels to the offline machine as JSON, where
+//! [`SigningPolicy::verify`] checks it before [`SigningRequest::message_bytes`] are signed:
+//!
+//! ```toml
+//! max_age_secs = 60
+//! max_land_slots = 150
+//! max_nefarious_leaders = 12
+//! max_slippage_if_nefarious = 50
+//! ```
+
+use crate::{
+    inspect::AntiSandwichInstruction,
+    policy::WindowRisk,
+    provider::{unix_now, Blocklist, BlocklistEntry},
+    AntiSandwichClient, AntiSandwichError, Result,
+};
+use anti_sandwich_common::NefariousWindow;
+use base64::{prelude::BASE64_STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use solana_message::VersionedMessage;
+use std::{fmt, ops::RangeInclusive};
+
+/// Version of the JSON format written by [`SigningRequest::to_json`].
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Where the window of a [`SigningRequest`] came from.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WindowProvenance {
+    /// Unix seconds at which the window was built.
+    pub built_at: u64,
+    /// Current slot when the window was built.
+    pub slot: u64,
+    /// Minimum confidence of the blocklist entries the window was built from.
+    #[serde(default)]
+    pub min_confidence: f64,
+    /// The blocklist entries the window was built from.
+    #[serde(default)]
+    pub entries: Vec<BlocklistEntry>,
+}
+
+impl WindowProvenance {
+    /// Provenance of a window built now at `slot` from the entries of `blocklist` that are active
+    /// with `min_confidence`.
+    pub fn from_blocklist(slot: u64, blocklist: &Blocklist, min_confidence: f64) -> Self {
+        let built_at = unix_now();
+        let mut entries: Vec<_> =
+            blocklist.active_entries(built_at, min_confidence).cloned().collect();
+        entries.sort_by_key(|entry| entry.identity);
+        Self { built_at, slot, min_confidence, entries }
+    }
+}
+
+/// An unsigned protected message with the window it was protected with.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SigningRequest {
+    pub message: VersionedMessage,
+    pub window: NefariousWindow,
+    pub provenance: WindowProvenance,
+    /// Slots the transaction is expected to land in.
+    pub land_range: RangeInclusive<u64>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EncodedWindow {
+    window_start: u64,
+    nefarious: [u8; 6],
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EncodedRequest {
+    version: u32,
+    /// Base64 of the serialized message.
+    message: String,
+    window: EncodedWindow,
+    provenance: WindowProvenance,
+    land_range: [u64; 2],
+}
+
+impl SigningRequest {
+    /// Request expected to land from the slot of `provenance` to the end of `window`.
+    pub fn new(
+        message: VersionedMessage,
+        window: NefariousWindow,
+        provenance: WindowProvenance,
+    ) -> Self {
+        let window_range = window.valid_land_range();
+        let first = provenance.slot.max(*window_range.start());
+        Self { message, window, provenance, land_range: first..=*window_range.end() }
+    }
+
+    pub fn with_land_range(mut self, land_range: RangeInclusive<u64>) -> Self {
+        self.land_range = land_range;
+        self
+    }
+
+    /// The bytes the signers sign.
+    pub fn message_bytes(&self) -> Vec<u8> {
+        self.message.serialize()
+    }
+
+    pub fn to_json(&self) -> String {
+        let encoded = EncodedRequest {
+            version: FORMAT_VERSION,
+            message: BASE64_STANDARD.encode(self.message_bytes()),
+            window: EncodedWindow {
+                window_start: self.window.window_start,
+                nefarious: self.window.nefarious,
+            },
+            provenance: self.provenance.clone(),
+            land_range: [*self.land_range.start(), *self.land_range.end()],
+        };
+        serde_json::to_string_pretty(&encoded).expect("signing requests always serialize")
+    }
+
+    pub fn from_json(s: &str) -> Result<Self> {
+        let invalid =
+            |err: &dyn fmt::Display| AntiSandwichError::InvalidSigningRequest(err.to_string());
+        let encoded: EncodedRequest = serde_json::from_str(s).map_err(|err| invalid(&err))?;
+        if encoded.version != FORMAT_VERSION {
+            return Err(invalid(&format!("unsupported version {}", encoded.version)));
+        }
+        let message = BASE64_STANDARD.decode(&encoded.message).map_err(|err| invalid(&err))?;
+        let message: VersionedMessage =
+            bincode::deserialize(&message).map_err(|err| invalid(&err))?;
+        // out-of-range account or program indices would otherwise only fail once submitted
+        message.sanitize().map_err(|err| invalid(&err))?;
+        let [first, last] = encoded.land_range;
+
+        Ok(Self {
+            message,
+            window: NefariousWindow {
+                window_start: encoded.window.window_start,
+                nefarious: encoded.window.nefarious,
+            },
+            provenance: encoded.provenance,
+            land_range: first..=last,
+        })
+    }
+}
+
+/// A constraint a [`SigningRequest`] does not meet.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Violation {
+    /// The message has no abort or adjust-slippage instruction of the program.
+    Unprotected,
+    /// Instruction `index` is for the program but its data does not decode.
+    InvalidInstruction {
+        index: usize,
+    },
+    /// Instruction `index` embeds a different window than the request.
+    WindowMismatch {
+        index: usize,
+        window: NefariousWindow,
+    },
+    /// The land range is empty, starts before the window was built or leaves the window.
+    InvalidLandRange {
+        first: u64,
+        last: u64,
+    },
+    LandRangeTooLong {
+        slots: u64,
+        max: u64,
+    },
+    StaleWindow {
+        age_secs: u64,
+        max: u64,
+    },
+    TooManyNefariousLeaders {
+        leaders: u32,
+        max: u32,
+    },
+    /// Instruction `index` lowers the slippage to more than the policy allows.
+    SlippageTooHigh {
+        index: usize,
+        slippage_if_nefarious: u16,
+        max: u16,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unprotected => write!(f, "message has no abort or adjust-slippage instruction"),
+            Self::InvalidInstruction { index } => {
+                write!(f, "instruction {index} has invalid data")
+            }
+            Self::WindowMismatch { index, .. } => {
+                write!(f, "instruction {index} embeds a different window than the request")
+            }
+            Self::InvalidLandRange { first, last } => {
+                write!(f, "land range {first}..={last} is not within the window")
+            }
+            Self::LandRangeTooLong { slots, max } => {
+                write!(f, "land range spans {slots} slots, more than {max}")
+            }
+            Self::StaleWindow { age_secs, max } => {
+                write!(f, "window is {age_secs}s old, more than {max}s")
+            }
+            Self::TooManyNefariousLeaders { leaders, max } => {
+                write!(f, "window flags {leaders} leaders, more than {max}")
+            }
+            Self::SlippageTooHigh { index, slippage_if_nefarious: bps, max } => {
+                write!(f, "instruction {index} lowers slippage to {bps} bps, more than {max}")
+            }
+        }
+    }
+}
+
+fn default_require_guard() -> bool {
+    true
+}
+
+/// What the offline machine requires of a [`SigningRequest`] beyond its guards embedding the
+/// request's window and its land range lying within that window. Unset limits are not checked.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SigningPolicy {
+    /// Reject messages without an abort or adjust-slippage instruction.
+    #[serde(default = "default_require_guard")]
+    pub require_guard: bool,
+    /// Maximum age of the window at verification time.
+    #[serde(default)]
+    pub max_age_secs: Option<u64>,
+    #[serde(default)]
+    pub max_land_slots: Option<u64>,
+    #[serde(default)]
+    pub max_nefarious_leaders: Option<u32>,
+    #[serde(default)]
+    pub max_slippage_if_nefarious: Option<u16>,
+}
+
+impl Default for SigningPolicy {
+    fn default() -> Self {
+        Self {
+            require_guard: default_require_guard(),
+            max_age_secs: None,
+            max_land_slots: None,
+            max_nefarious_leaders: None,
+            max_slippage_if_nefarious: None,
+        }
+    }
+}
+
+impl SigningPolicy {
+    pub fn from_toml(s: &str) -> Result<Self> {
+        toml::from_str(s).map_err(|err| AntiSandwichError::InvalidConfig(err.to_string()))
+    }
+
+    pub fn from_json(s: &str) -> Result<Self> {
+        serde_json::from_str(s).map_err(|err| AntiSandwichError::InvalidConfig(err.to_string()))
+    }
+
+    /// Verify `request` against the upstream program now. Use
+    /// [`AntiSandwichClient::verify_signing_request`] for other deployments.
+    pub fn verify(&self, request: &SigningRequest) -> Result<()> {
+        AntiSandwichClient::default().verify_signing_request(self, request, unix_now())
+    }
+}
+
+impl AntiSandwichClient {
+    /// Every constraint of `policy` that `request` violates at unix time `now`, including guards
+    /// of this deployment that embed a window other than the request's.
+    pub fn signing_violations(
+        &self,
+        policy: &SigningPolicy,
+        request: &SigningRequest,
+        now: u64,
+    ) -> Vec<Violation> {
+        let mut violations = vec![];
+        let window = &request.window;
+        let (first, last) = (*request.land_range.start(), *request.land_range.end());
+
+        let mut protected = false;
+        for inspection in self.inspect(&request.message, first) {
+            let index = inspection.index;
+            let Some(instruction) = inspection.instruction else {
+                violations.push(Violation::InvalidInstruction { index });
+                continue;
+            };
+            if instruction.window() != window {
+                violations.push(Violation::WindowMismatch { index, window: *instruction.window() });
+            }
+            match instruction {
+                AntiSandwichInstruction::AbortIfNefarious { .. } => protected = true,
+                AntiSandwichInstruction::AdjustSlippage { slippage_if_nefarious, .. } => {
+                    protected = true;
+                    if let Some(max) = policy.max_slippage_if_nefarious {
+                        if slippage_if_nefarious > max {
+                            violations.push(Violation::SlippageTooHigh {
+                                index,
+                                slippage_if_nefarious,
+                                max,
+                            });
+                        }
+                    }
+                }
+                AntiSandwichInstruction::ReportIfNefarious { .. } => {}
+            }
+        }
+        if policy.require_guard && !protected {
+            violations.push(Violation::Unprotected);
+        }
+
+        let window_range = window.valid_land_range();
+        if first > last
+            || first < request.provenance.slot
+            || !window_range.contains(&first)
+            || !window_range.contains(&last)
+        {
+            violations.push(Violation::InvalidLandRange { first, last });
+        }
+        if let Some(max) = policy.max_land_slots {
+            let slots = (last + 1).saturating_sub(first);
+            if slots > max {
+                violations.push(Violation::LandRangeTooLong { slots, max });
+            }
+        }
+        if let Some(max) = policy.max_age_secs {
+            let age_secs = now.saturating_sub(request.provenance.built_at);
+            if age_secs > max {
+                violations.push(Violation::StaleWindow { age_secs, max });
+            }
+        }
+        if let Some(max) = policy.max_nefarious_leaders {
+            let leaders = WindowRisk::from(window).nefarious_leaders;
+            if leaders > max {
+                violations.push(Violation::TooManyNefariousLeaders { leaders, max });
+            }
+        }
+        violations
+    }
+
+    /// Fail with [`AntiSandwichError::InvalidSigningRequest`] listing every violation of
+    /// [`AntiSandwichClient::signing_violations`].
+    pub fn verify_signing_request(
+        &self,
+        policy: &SigningPolicy,
+        request: &SigningRequest,
+        now: u64,
+    ) -> Result<()> {
+        let violations = self.signing_violations(policy, request, now);
+        if violations.is_empty() {
+            return Ok(());
+        }
+        let violations: Vec<_> = violations.iter().map(Violation::to_string).collect();
+        Err(AntiSandwichError::InvalidSigningRequest(violations.join("; ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        abort_if_nefarious, adjust_slippage_at_runtime, jupiter::tests::SAMPLE_ROUTE, JUPITER_V6,
+    };
+    use solana_instruction::Instruction;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+
+    const WINDOW: NefariousWindow =
+        NefariousWindow { window_start: 350_000_000, nefarious: [0b11, 0, 0, 0, 0, 0] };
+
+    fn message(instructions: &[Instruction]) -> VersionedMessage {
+        VersionedMessage::Legacy(Message::new(instructions, Some(&Pubkey::new_unique())))
+    }
+
+    fn provenance() -> WindowProvenance {
+        WindowProvenance { built_at: 1_000, slot: 350_000_010, ..WindowProvenance::default() }
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let flagged = Pubkey::new_unique();
+        let blocklist = Blocklist::from_entries([BlocklistEntry {
+            source: Some("feed".to_string()),
+            confidence: 0.9,
+            evidence: vec!["tx1".to_string()],
+            ..BlocklistEntry::new(flagged)
+        }])
+        .unwrap();
+        let provenance = WindowProvenance::from_blocklist(350_000_010, &blocklist, 0.5);
+        assert_eq!(provenance.entries, blocklist.entries().cloned().collect::<Vec<_>>());
+
+        let request = SigningRequest::new(
+            message(&[abort_if_nefarious(&WINDOW).unwrap()]),
+            WINDOW,
+            provenance,
+        );
+        assert_eq!(request.land_range, 350_000_010..=350_000_191);
+
+        let json = request.to_json();
+        assert!(json.contains(&flagged.to_string()));
+        assert_eq!(SigningRequest::from_json(&json).unwrap(), request);
+
+        let future = json.replace("\"version\": 1", "\"version\": 2");
+        assert!(matches!(
+            SigningRequest::from_json(&future),
+            Err(AntiSandwichError::InvalidSigningRequest(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_unsanitized_message() {
+        let mut request = SigningRequest::new(
+            message(&[abort_if_nefarious(&WINDOW).unwrap()]),
+            WINDOW,
+            provenance(),
+        );
+        let VersionedMessage::Legacy(legacy) = &mut request.message else { unreachable!() };
+        legacy.instructions[0].program_id_index = 99;
+
+        assert!(matches!(
+            SigningRequest::from_json(&request.to_json()),
+            Err(AntiSandwichError::InvalidSigningRequest(_))
+        ));
+    }
+
+    #[test]
+    fn verifies_window_and_policy() {
+        let route =
+            Instruction { program_id: JUPITER_V6, accounts: vec![], data: SAMPLE_ROUTE.to_vec() };
+        let request = SigningRequest::new(
+            message(&[adjust_slippage_at_runtime(&WINDOW, 50, route.clone()).unwrap()]),
+            WINDOW,
+            provenance(),
+        );
+        let policy = SigningPolicy::from_toml(
+            "max_age_secs = 60\nmax_land_slots = 150\nmax_nefarious_leaders = 2\n\
+             max_slippage_if_nefarious = 50",
+        )
+        .unwrap();
+        let client = AntiSandwichClient::default();
+
+        let short = request.clone().with_land_range(350_000_010..=350_000_150);
+        assert_eq!(client.verify_signing_request(&policy, &short, 1_060), Ok(()));
+        assert_eq!(
+            client.signing_violations(&policy, &request, 1_061),
+            [
+                Violation::LandRangeTooLong { slots: 182, max: 150 },
+                Violation::StaleWindow { age_secs: 61, max: 60 },
+            ]
+        );
+
+        // a guard built with another window, a looser slippage and a land range that starts
+        // before the window was built
+        let other = NefariousWindow { nefarious: [0b111, 0, 0, 0, 0, 0], ..WINDOW };
+        let tampered = SigningRequest {
+            message: message(&[
+                abort_if_nefarious(&other).unwrap(),
+                adjust_slippage_at_runtime(&WINDOW, 300, route).unwrap(),
+            ]),
+            land_range: 350_000_000..=350_000_100,
+            ..short
+        };
+        assert_eq!(
+            client.signing_violations(&policy, &tampered, 1_000),
+            [
+                Violation::WindowMismatch { index: 0, window: other },
+                Violation::SlippageTooHigh { index: 1, slippage_if_nefarious: 300, max: 50 },
+                Violation::InvalidLandRange { first: 350_000_000, last: 350_000_100 },
+            ]
+        );
+
+        let unprotected = SigningRequest { message: message(&[]), ..tampered };
+        let err = policy.verify(&unprotected).unwrap_err().to_string();
+        assert!(err.contains("no abort or adjust-slippage instruction"), "{err}");
+    }
+}
@@ -0,0 +1,118 @@
+//! Classification of failed transactions.
+//!
+//! A protected transaction fails either because a guard aborted it on a nefarious leader, because
+//! the swap still ran out of slippage after the adjustment, or for reasons unrelated to the
+//! program. [`classify_transaction_error`] tells these apart from the `TransactionError` returned
+//! by simulation or `getSignatureStatuses`.
+
+pub use anti_sandwich_common::ErrorCode;
+
+use crate::{AntiSandwichClient, ABORT_DISC, ADJUST_SLIPPAGE_DISC};
+use solana_instruction::error::InstructionError;
+use solana_message::VersionedMessage;
+use solana_transaction_error::TransactionError;
+
+/// Jupiter's `SlippageToleranceExceeded`, bubbled up through the adjust-slippage CPI.
+pub const JUPITER_SLIPPAGE_TOLERANCE_EXCEEDED: u32 = 0x1771;
+
+/// Why a transaction failed. `index` is the top-level instruction responsible.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TransactionFailure {
+    /// An `abort_if_nefarious` guard ran on a flagged leader.
+    Aborted { index: usize },
+    /// The wrapped Jupiter swap exceeded its slippage tolerance. Whether the slippage was lowered
+    /// first depends on the landed slot, see [`crate::inspect::inspect`].
+    SlippageExceeded { index: usize },
+    /// Any other failure. `index` is `None` for errors not tied to an instruction.
+    Other { index: Option<usize> },
+}
+
+/// Classify a failure of a transaction protected by the upstream program. Use
+/// [`AntiSandwichClient::classify_transaction_error`] for other deployments.
+pub fn classify_transaction_error(
+    err: &TransactionError,
+    message: &VersionedMessage,
+) -> TransactionFailure {
+    AntiSandwichClient::default().classify_transaction_error(err, message)
+}
+
+impl AntiSandwichClient {
+    /// Classify `err`, the failure of the transaction carrying `message`.
+    pub fn classify_transaction_error(
+        &self,
+        err: &TransactionError,
+        message: &VersionedMessage,
+    ) -> TransactionFailure {
+        let TransactionError::InstructionError(index, err) = err else {
+            return TransactionFailure::Other { index: None };
+        };
+        let index = *index as usize;
+
+        let disc = message
+            .instructions()
+            .get(index)
+            .filter(|ix| {
+                message.static_account_keys().get(ix.program_id_index as usize)
+                    == Some(&self.program_id)
+            })
+            .and_then(|ix| ix.data.first().copied());
+        match (disc, err) {
+            (Some(ABORT_DISC), InstructionError::Custom(code))
+                if ErrorCode::try_from(*code) == Ok(ErrorCode::NefariousLeader) =>
+            {
+                TransactionFailure::Aborted { index }
+            }
+            (
+                Some(ADJUST_SLIPPAGE_DISC),
+                InstructionError::Custom(JUPITER_SLIPPAGE_TOLERANCE_EXCEEDED),
+            ) => TransactionFailure::SlippageExceeded { index },
+            _ => TransactionFailure::Other { index: Some(index) },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        abort_if_nefarious, adjust_slippage_at_runtime, jupiter::tests::SAMPLE_ROUTE, JUPITER_V6,
+    };
+    use anti_sandwich_common::NefariousWindow;
+    use solana_instruction::Instruction;
+    use solana_message::Message;
+    use solana_pubkey::Pubkey;
+
+    #[test]
+    fn classifies_failures() {
+        let window = NefariousWindow::empty();
+        let route =
+            Instruction { program_id: JUPITER_V6, accounts: vec![], data: SAMPLE_ROUTE.to_vec() };
+        let other =
+            Instruction { program_id: Pubkey::new_unique(), accounts: vec![], data: vec![] };
+        let payer = Pubkey::new_unique();
+        let message = VersionedMessage::Legacy(Message::new(
+            &[
+                other,
+                abort_if_nefarious(&window).unwrap(),
+                adjust_slippage_at_runtime(&window, 50, route).unwrap(),
+            ],
+            Some(&payer),
+        ));
+        let custom = |index, code| {
+            classify_transaction_error(
+                &TransactionError::InstructionError(index, InstructionError::Custom(code)),
+                &message,
+            )
+        };
+
+        assert_eq!(custom(1, 100), TransactionFailure::Aborted { index: 1 });
+        assert_eq!(custom(2, 0x1771), TransactionFailure::SlippageExceeded { index: 2 });
+        // the codes only count for the instruction of the matching processor
+        assert_eq!(custom(2, 100), TransactionFailure::Other { index: Some(2) });
+        assert_eq!(custom(0, 100), TransactionFailure::Other { index: Some(0) });
+        assert_eq!(
+            classify_transaction_error(&TransactionError::AccountNotFound, &message),
+            TransactionFailure::Other { index: None }
+        );
+    }
+}
@@ -0,0 +1,375 @@
+//! Declarative choice of a [`Protection`] per swap.
+//!
+//! A [`Policy`] is an ordered list of rules loaded from TOML or JSON. The first rule whose
+//! conditions all hold for a swap decides its protection:
+//!
+//! ```toml
+//! default = { action = "abort" }
+//!
+//! [[rules]]
+//! name = "dust"
+//! max_notional = 100.0
+//! protection = { action = "none" }
+//!
+//! [[rules]]
+//! name = "majors"
+//! both_mints = ["So11111111111111111111111111111111111111112", "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"]
+//! protection = { action = "adjust_slippage", slippage_if_nefarious = 50 }
+//!
+//! [[rules]]
+//! name = "large memecoin swaps"
+//! any_mint = ["DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263"]
+//! min_notional = 10000.0
+//! protection = { action = "abort" }
+//! ```
+
+use crate::{
+    swap::{ProtectedSwap, Protection, SwapInstructions},
+    AntiSandwichClient, AntiSandwichError, Result, WindowSource,
+};
+use anti_sandwich_common::NefariousWindow;
+use serde::{de, Deserialize, Deserializer};
+use solana_pubkey::Pubkey;
+use std::{collections::HashSet, str::FromStr};
+
+/// The swap a policy is applied to. The SDK has no price source, so the notional (in whatever
+/// unit the policy's thresholds use, e.g. USD) comes from the caller's quote.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SwapContext {
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    pub notional: f64,
+}
+
+/// How many leaders of a window are flagged.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct WindowRisk {
+    pub nefarious_leaders: u32,
+}
+
+impl WindowRisk {
+    /// Share of the window's leaders that are flagged, in `0.0..=1.0`.
+    pub fn nefarious_share(&self) -> f64 {
+        self.nefarious_leaders as f64 / NefariousWindow::MAX_LEADERS as f64
+    }
+}
+
+impl From<&NefariousWindow> for WindowRisk {
+    fn from(window: &NefariousWindow) -> Self {
+        Self { nefarious_leaders: window.nefarious.iter().map(|b| b.count_ones()).sum() }
+    }
+}
+
+/// One rule of a [`Policy`]. Unset conditions always hold.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    /// Identifies the rule in configuration errors; callers can log it from
+    /// [`Policy::matching_rule`].
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default, deserialize_with = "pubkeys")]
+    pub input_mints: Option<HashSet<Pubkey>>,
+    #[serde(default, deserialize_with = "pubkeys")]
+    pub output_mints: Option<HashSet<Pubkey>>,
+    /// The input or the output mint is one of these.
+    #[serde(default, deserialize_with = "pubkeys")]
+    pub any_mint: Option<HashSet<Pubkey>>,
+    /// The input and the output mint are both among these.
+    #[serde(default, deserialize_with = "pubkeys")]
+    pub both_mints: Option<HashSet<Pubkey>>,
+    /// Inclusive lower bound of the notional.
+    #[serde(default)]
+    pub min_notional: Option<f64>,
+    /// Exclusive upper bound of the notional.
+    #[serde(default)]
+    pub max_notional: Option<f64>,
+    /// Inclusive bounds of [`WindowRisk::nefarious_leaders`].
+    #[serde(default)]
+    pub min_nefarious_leaders: Option<u32>,
+    #[serde(default)]
+    pub max_nefarious_leaders: Option<u32>,
+    pub protection: Protection,
+}
+
+fn pubkeys<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<HashSet<Pubkey>>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|key| Pubkey::from_str(key).map_err(|err| de::Error::custom(format!("{key}: {err}"))))
+        .collect::<Result<_, _>>()
+        .map(Some)
+}
+
+impl Rule {
+    /// Conditions that no swap can meet, reported with the name or the position of the rule.
+    fn validate(&self, index: usize) -> Result<()> {
+        let label = match &self.name {
+            Some(name) => format!("rule {name:?}"),
+            None => format!("rule {}", index + 1),
+        };
+        if let (Some(min), Some(max)) = (self.min_notional, self.max_notional) {
+            if min >= max {
+                return Err(AntiSandwichError::InvalidConfig(format!(
+                    "{label}: min_notional {min} is not below max_notional {max}"
+                )));
+            }
+        }
+        if let (Some(min), Some(max)) = (self.min_nefarious_leaders, self.max_nefarious_leaders) {
+            if min > max {
+                return Err(AntiSandwichError::InvalidConfig(format!(
+                    "{label}: min_nefarious_leaders {min} is above max_nefarious_leaders {max}"
+                )));
+            }
+        }
+        validate_protection(&label, self.protection)
+    }
+
+    pub fn matches(&self, swap: &SwapContext, risk: &WindowRisk) -> bool {
+        let in_set = |set: &Option<HashSet<Pubkey>>, mint: &Pubkey| {
+            set.as_ref().is_none_or(|set| set.contains(mint))
+        };
+
+        in_set(&self.input_mints, &swap.input_mint)
+            && in_set(&self.output_mints, &swap.output_mint)
+            && self
+                .any_mint
+                .as_ref()
+                .is_none_or(|set| set.contains(&swap.input_mint) || set.contains(&swap.output_mint))
+            && in_set(&self.both_mints, &swap.input_mint)
+            && in_set(&self.both_mints, &swap.output_mint)
+            && self.min_notional.is_none_or(|min| swap.notional >= min)
+            && self.max_notional.is_none_or(|max| swap.notional < max)
+            && self.min_nefarious_leaders.is_none_or(|min| risk.nefarious_leaders >= min)
+            && self.max_nefarious_leaders.is_none_or(|max| risk.nefarious_leaders <= max)
+    }
+}
+
+/// Slippage above 100% that no route can be adjusted to.
+fn validate_protection(label: &str, protection: Protection) -> Result<()> {
+    match protection {
+        Protection::AdjustSlippage { slippage_if_nefarious } if slippage_if_nefarious > 10_000 => {
+            Err(AntiSandwichError::InvalidConfig(format!(
+                "{label}: slippage_if_nefarious {slippage_if_nefarious} is above 10000 bps"
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn default_protection() -> Protection {
+    Protection::Abort
+}
+
+/// Ordered protection rules. The first matching rule wins; without a match, `default` applies,
+/// which is [`Protection::Abort`] unless configured.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    #[serde(default = "default_protection")]
+    pub default: Protection,
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+}
+
+impl Policy {
+    pub fn from_toml(s: &str) -> Result<Self> {
+        toml::from_str::<Self>(s)
+            .map_err(|err| AntiSandwichError::InvalidConfig(err.to_string()))?
+            .validated()
+    }
+
+    pub fn from_json(s: &str) -> Result<Self> {
+        serde_json::from_str::<Self>(s)
+            .map_err(|err| AntiSandwichError::InvalidConfig(err.to_string()))?
+            .validated()
+    }
+
+    fn validated(self) -> Result<Self> {
+        validate_protection("default", self.default)?;
+        for (index, rule) in self.rules.iter().enumerate() {
+            rule.validate(index)?;
+        }
+        Ok(self)
+    }
+
+    /// The rule deciding the protection of `swap` when the window carries `risk`, `None` if the
+    /// default applies.
+    pub fn matching_rule(&self, swap: &SwapContext, risk: &WindowRisk) -> Option<&Rule> {
+        self.rules.iter().find(|rule| rule.matches(swap, risk))
+    }
+
+    /// Protection for `swap` when the window carries `risk`.
+    pub fn protection(&self, swap: &SwapContext, risk: &WindowRisk) -> Protection {
+        self.matching_rule(swap, risk).map_or(self.default, |rule| rule.protection)
+    }
+
+    /// Protect `swap` against the upstream program. Use [`AntiSandwichClient::apply_policy`] for
+    /// other deployments.
+    pub fn apply<W: WindowSource + ?Sized>(
+        &self,
+        source: &W,
+        context: &SwapContext,
+        swap: impl Into<SwapInstructions>,
+    ) -> Result<ProtectedSwap> {
+        AntiSandwichClient::default().apply_policy(self, source, context, swap)
+    }
+}
+
+impl AntiSandwichClient {
+    /// [`AntiSandwichClient::protect_swap`] with the protection `policy` picks for `context` and
+    /// the window of `source`.
+    pub fn apply_policy<W: WindowSource + ?Sized>(
+        &self,
+        policy: &Policy,
+        source: &W,
+        context: &SwapContext,
+        swap: impl Into<SwapInstructions>,
+    ) -> Result<ProtectedSwap> {
+        let window = source.window()?;
+        let protection = policy.protection(context, &WindowRisk::from(&window));
+        self.protect_swap(&window, swap, protection)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        jupiter::{tests::SAMPLE_ROUTE, RouteArgs},
+        JUPITER_V6, PROGRAM_ID,
+    };
+    use solana_instruction::Instruction;
+
+    const SOL: &str = "So11111111111111111111111111111111111111112";
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+    const BONK: &str = "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263";
+
+    fn swap(input: &str, output: &str, notional: f64) -> SwapContext {
+        SwapContext {
+            input_mint: Pubkey::from_str(input).unwrap(),
+            output_mint: Pubkey::from_str(output).unwrap(),
+            notional,
+        }
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let policy = Policy::from_toml(&format!(
+            r#"
+            default = {{ action = "adjust_slippage", slippage_if_nefarious = 100 }}
+
+            [[rules]]
+            name = "dust"
+            max_notional = 100.0
+            protection = {{ action = "none" }}
+
+            [[rules]]
+            both_mints = ["{SOL}", "{USDC}"]
+            protection = {{ action = "adjust_slippage", slippage_if_nefarious = 50 }}
+
+            [[rules]]
+            any_mint = ["{BONK}"]
+            min_notional = 10000.0
+            protection = {{ action = "abort" }}
+
+            [[rules]]
+            min_nefarious_leaders = 12
+            protection = {{ action = "abort" }}
+            "#
+        ))
+        .unwrap();
+        let calm = WindowRisk { nefarious_leaders: 0 };
+
+        assert_eq!(policy.protection(&swap(BONK, SOL, 50.0), &calm), Protection::None);
+        let dust = policy.matching_rule(&swap(BONK, SOL, 50.0), &calm).unwrap();
+        assert_eq!(dust.name.as_deref(), Some("dust"));
+        assert_eq!(policy.matching_rule(&swap(USDC, BONK, 5_000.0), &calm), None);
+        assert_eq!(
+            policy.protection(&swap(USDC, SOL, 1e6), &calm),
+            Protection::AdjustSlippage { slippage_if_nefarious: 50 }
+        );
+        assert_eq!(policy.protection(&swap(USDC, BONK, 20_000.0), &calm), Protection::Abort);
+        assert_eq!(
+            policy.protection(&swap(USDC, BONK, 5_000.0), &calm),
+            Protection::AdjustSlippage { slippage_if_nefarious: 100 }
+        );
+        let risky = WindowRisk { nefarious_leaders: 12 };
+        assert_eq!(policy.protection(&swap(USDC, BONK, 5_000.0), &risky), Protection::Abort);
+        assert_eq!(risky.nefarious_share(), 0.25);
+
+        let json = Policy::from_json(&format!(
+            r#"{{"rules": [{{"input_mints": ["{SOL}"], "protection": {{"action": "none"}}}}]}}"#
+        ))
+        .unwrap();
+        assert_eq!(json.protection(&swap(SOL, USDC, 1.0), &calm), Protection::None);
+        assert_eq!(json.protection(&swap(USDC, SOL, 1.0), &calm), Protection::Abort);
+
+        assert!(Policy::from_json(
+            r#"{"rules": [{"any_mint": ["x"], "protection": {"action": "abort"}}]}"#
+        )
+        .is_err());
+        assert!(Policy::from_toml("default = { action = \"abort\" }\nrule = []").is_err());
+
+        let empty = Policy::from_toml(
+            "[[rules]]\nname = \"whales\"\nmin_notional = 1e6\nmax_notional = 1e3\n\
+             protection = { action = \"abort\" }",
+        );
+        let Err(AntiSandwichError::InvalidConfig(err)) = empty else {
+            panic!("expected InvalidConfig, got {empty:?}");
+        };
+        assert!(err.starts_with("rule \"whales\": min_notional"), "{err}");
+
+        let loose = "[[rules]]\n\
+                     protection = { action = \"adjust_slippage\", slippage_if_nefarious = 10001 }";
+        let Err(AntiSandwichError::InvalidConfig(err)) = Policy::from_toml(loose) else {
+            panic!("expected InvalidConfig");
+        };
+        assert!(err.starts_with("rule 1: slippage_if_nefarious 10001"), "{err}");
+    }
+
+    #[test]
+    fn applies_to_swap() {
+        let policy = Policy::from_json(
+            r#"{"rules": [{"min_nefarious_leaders": 1, "protection": {"action": "abort"}}],
+                "default": {"action": "none"}}"#,
+        )
+        .unwrap();
+        let route =
+            Instruction { program_id: JUPITER_V6, accounts: vec![], data: SAMPLE_ROUTE.to_vec() };
+        let context = swap(SOL, USDC, 1.0);
+
+        let clean = policy
+            .apply(&NefariousWindow::empty(), &context, SwapInstructions::new(route.clone()))
+            .unwrap();
+        assert_eq!(clean.instructions, std::slice::from_ref(&route));
+
+        let flagged = NefariousWindow { window_start: 350_000_000, nefarious: [1, 0, 0, 0, 0, 0] };
+        let guarded = policy.apply(&flagged, &context, SwapInstructions::new(route)).unwrap();
+        assert!(guarded.instructions.iter().any(|ix| ix.program_id == PROGRAM_ID));
+    }
+
+    #[test]
+    fn keeps_tighter_quote() {
+        let policy = Policy::from_json(
+            r#"{"default": {"action": "adjust_slippage", "slippage_if_nefarious": 50}}"#,
+        )
+        .unwrap();
+        let context = swap(SOL, USDC, 1.0);
+        let slippage = |quoted: u16| {
+            let route =
+                RouteArgs { slippage_bps: quoted, ..RouteArgs::decode(&SAMPLE_ROUTE).unwrap() };
+            let route =
+                Instruction { program_id: JUPITER_V6, accounts: vec![], data: route.encode() };
+            let protected = policy
+                .apply(&NefariousWindow::empty(), &context, SwapInstructions::new(route))
+                .unwrap();
+            let ix = protected.instructions.iter().find(|ix| ix.program_id == PROGRAM_ID).unwrap();
+            u16::from_le_bytes([ix.data[15], ix.data[16]])
+        };
+
+        assert_eq!(slippage(30), 30);
+        assert_eq!(slippage(300), 50);
+    }
+}
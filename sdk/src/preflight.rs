@@ -0,0 +1,232 @@
+//! Local preflight of a protected transaction across its land range.
+//!
+//! Whether the guard aborts, the slippage is lowered or nothing happens depends on the slot the
+//! transaction lands in. [`Preflight::sweep`] executes the transaction in LiteSVM at every slot of
+//! its window and reports the outcome and compute units of each, like the example `Simulator`
+//! does for a single slot.
+//!
+//! The accounts the transaction loads, lookup tables included, have to be provided, e.g. fetched
+//! with `getMultipleAccounts` right before the sweep.
+
+use crate::{
+    inspect::Verdict, outcome::TransactionFailure, AntiSandwichClient, AntiSandwichError, Result,
+};
+use litesvm::LiteSVM;
+use solana_account::Account;
+use solana_pubkey::Pubkey;
+use solana_transaction::versioned::VersionedTransaction;
+use solana_transaction_error::TransactionError;
+use std::path::Path;
+
+/// What happens to the transaction when it lands in a slot.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SlotOutcome {
+    /// It succeeds and no swap slippage is changed.
+    Ok,
+    /// A guard fails it with `ErrorCode::NefariousLeader`.
+    Aborted,
+    /// It succeeds and a wrapped swap's slippage is lowered. The execution doesn't report the
+    /// slippage, so this is inferred from the window and data of the instruction, see
+    /// [`crate::inspect::inspect`].
+    Adjusted { from_bps: u16, to_bps: u16 },
+    /// It fails for any other reason, e.g. the lowered slippage is exceeded.
+    Failed(TransactionError),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SlotResult {
+    pub slot: u64,
+    pub outcome: SlotOutcome,
+    pub compute_units: u64,
+}
+
+pub struct Preflight {
+    client: AntiSandwichClient,
+    svm: LiteSVM,
+}
+
+impl Preflight {
+    /// LiteSVM with the compiled program (`target/deploy/anti_sandwich_program.so`) deployed at
+    /// [`PROGRAM_ID`](crate::PROGRAM_ID). Signatures and blockhashes are not checked, so unsigned
+    /// or old transactions can be swept.
+    pub fn new(program_path: impl AsRef<Path>) -> Result<Self> {
+        Self::with_client(AntiSandwichClient::default(), program_path)
+    }
+
+    /// Like [`Preflight::new`], with the program deployed at the client's program id.
+    pub fn with_client(client: AntiSandwichClient, program_path: impl AsRef<Path>) -> Result<Self> {
+        let mut svm = LiteSVM::new()
+            .with_precompiles()
+            .with_sysvars()
+            .with_builtins()
+            .with_sigverify(false)
+            .with_blockhash_check(false);
+        svm.add_program_from_file(client.program_id, program_path)?;
+        Ok(Self { client, svm })
+    }
+
+    /// Load an account, lookup tables and Jupiter or AMM programs included.
+    pub fn add_account(&mut self, pubkey: Pubkey, account: Account) -> Result<()> {
+        self.svm
+            .set_account(pubkey, account)
+            .map_err(|err| AntiSandwichError::InvalidConfig(format!("account {pubkey}: {err}")))
+    }
+
+    pub fn add_accounts(
+        &mut self,
+        accounts: impl IntoIterator<Item = (Pubkey, Account)>,
+    ) -> Result<()> {
+        accounts.into_iter().try_for_each(|(pubkey, account)| self.add_account(pubkey, account))
+    }
+
+    /// Execute `tx` at every slot of the window of its first anti-sandwich instruction. Nothing
+    /// is committed, so every slot starts from the loaded accounts.
+    pub fn sweep(&mut self, tx: &VersionedTransaction) -> Result<Vec<SlotResult>> {
+        let inspections = self.client.inspect(&tx.message, 0);
+        let window = inspections
+            .iter()
+            .find_map(|inspection| inspection.instruction.as_ref())
+            .map(|instruction| *instruction.window())
+            .ok_or(AntiSandwichError::NoTargetInstruction { program_id: self.client.program_id })?;
+
+        Ok(window.valid_land_range().map(|slot| self.run_at(tx, slot)).collect())
+    }
+
+    fn run_at(&mut self, tx: &VersionedTransaction, slot: u64) -> SlotResult {
+        self.svm.warp_to_slot(slot);
+
+        match self.svm.simulate_transaction(tx.clone()) {
+            Ok(info) => {
+                let adjusted =
+                    self.client.inspect(&tx.message, slot).into_iter().find_map(|inspection| {
+                        match inspection.verdict {
+                            Verdict::SlippageAdjusted { from_bps, to_bps } => {
+                                Some(SlotOutcome::Adjusted { from_bps, to_bps })
+                            }
+                            _ => None,
+                        }
+                    });
+                SlotResult {
+                    slot,
+                    outcome: adjusted.unwrap_or(SlotOutcome::Ok),
+                    compute_units: info.meta.compute_units_consumed,
+                }
+            }
+            Err(failed) => {
+                let outcome = match self.client.classify_transaction_error(&failed.err, &tx.message)
+                {
+                    TransactionFailure::Aborted { .. } => SlotOutcome::Aborted,
+                    _ => SlotOutcome::Failed(failed.err),
+                };
+                SlotResult { slot, outcome, compute_units: failed.meta.compute_units_consumed }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        abort_if_nefarious, adjust_slippage_at_runtime, compute_budget::processor_overhead,
+        jupiter::tests::SAMPLE_ROUTE, report_if_nefarious, ABORT_DISC, ADJUST_SLIPPAGE_DISC,
+        JUPITER_V6, REPORT_DISC,
+    };
+    use anti_sandwich_common::NefariousWindow;
+    use solana_hash::Hash;
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_keypair::Keypair;
+    use solana_message::{v0, VersionedMessage};
+    use solana_signer::Signer;
+
+    /// Path of the program built with `cargo build-sbf`.
+    const PROGRAM_SO_ENV: &str = "ANTI_SANDWICH_PROGRAM_SO";
+
+    fn program() -> String {
+        std::env::var(PROGRAM_SO_ENV).unwrap_or_else(|_| panic!("{PROGRAM_SO_ENV} is not set"))
+    }
+
+    #[test]
+    #[ignore = "needs ANTI_SANDWICH_PROGRAM_SO"]
+    fn sweeps_land_range() {
+        let user = Keypair::new();
+        let mut preflight = Preflight::new(program()).unwrap();
+        preflight
+            .add_account(user.pubkey(), Account { lamports: 1_000_000_000, ..Account::default() })
+            .unwrap();
+
+        let window = NefariousWindow { window_start: 350_000_000, nefarious: [1, 0, 0, 0, 0, 0] };
+        let guard = abort_if_nefarious(&window).unwrap();
+        let message = v0::Message::try_compile(&user.pubkey(), &[guard], &[], Hash::default());
+        let tx = VersionedTransaction::try_new(VersionedMessage::V0(message.unwrap()), &[&user])
+            .unwrap();
+
+        let results = preflight.sweep(&tx).unwrap();
+        assert_eq!(results.len(), 192);
+        assert!(results[..4].iter().all(|r| r.outcome == SlotOutcome::Aborted));
+        assert!(results[4..].iter().all(|r| r.outcome == SlotOutcome::Ok));
+        assert_eq!(results[4].slot, 350_000_004);
+        assert!(results.iter().all(|r| r.compute_units > 0));
+    }
+
+    /// Compute units `ixs` consume, whether they succeed or not.
+    fn compute_units(preflight: &mut Preflight, user: &Keypair, ixs: &[Instruction]) -> u64 {
+        let message = v0::Message::try_compile(&user.pubkey(), ixs, &[], Hash::default());
+        let tx =
+            VersionedTransaction::try_new(VersionedMessage::V0(message.unwrap()), &[user]).unwrap();
+        // outside the flagged rotation, so guards pass and slippage is kept
+        preflight.run_at(&tx, 350_000_004).compute_units
+    }
+
+    /// Measures every processor and fails if one costs more than
+    /// [`processor_overhead`] allows. The guards run on their own, so their cost is the whole
+    /// transaction's. The wrapper forwards a route to the Jupiter program from
+    /// `examples/testdata`, which rejects it the same way with or without the wrapper, so the
+    /// difference to the bare route is the wrapper's cost.
+    #[test]
+    #[ignore = "needs ANTI_SANDWICH_PROGRAM_SO"]
+    fn measures_processor_overhead() {
+        let user = Keypair::new();
+        let mut preflight = Preflight::new(program()).unwrap();
+        preflight
+            .add_account(user.pubkey(), Account { lamports: 1_000_000_000, ..Account::default() })
+            .unwrap();
+        let jupiter = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../examples/testdata/JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4.so"
+        );
+        preflight.svm.add_program_from_file(JUPITER_V6, jupiter).unwrap();
+        let window = NefariousWindow { window_start: 350_000_000, nefarious: [1, 0, 0, 0, 0, 0] };
+
+        for (disc, ix) in [
+            (ABORT_DISC, abort_if_nefarious(&window).unwrap()),
+            (REPORT_DISC, report_if_nefarious(window)),
+        ] {
+            let measured = compute_units(&mut preflight, &user, &[ix]);
+            let overhead = processor_overhead(disc, 0).unwrap();
+            eprintln!("processor {disc}: {measured} CU, table {overhead}");
+            assert!(measured <= u64::from(overhead), "processor {disc}: {measured} CU");
+        }
+
+        for accounts in [0, 16, 64] {
+            let accounts = (0..accounts)
+                .map(|_| AccountMeta::new_readonly(Pubkey::new_unique(), false))
+                .collect::<Vec<_>>();
+            let route =
+                Instruction { program_id: JUPITER_V6, accounts, data: SAMPLE_ROUTE.to_vec() };
+            let bare = compute_units(&mut preflight, &user, std::slice::from_ref(&route));
+            let wrapped = adjust_slippage_at_runtime(&window, 50, route.clone()).unwrap();
+            let measured = compute_units(&mut preflight, &user, &[wrapped]) - bare;
+            let overhead = processor_overhead(ADJUST_SLIPPAGE_DISC, route.accounts.len()).unwrap();
+            eprintln!(
+                "adjust with {} accounts: {measured} CU, table {overhead}",
+                route.accounts.len()
+            );
+            assert!(
+                measured <= u64::from(overhead),
+                "{} accounts: {measured} CU",
+                route.accounts.len()
+            );
+        }
+    }
+}
@@ -0,0 +1,321 @@
+use crate::{AntiSandwichError, Result};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use solana_pubkey::Pubkey;
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// A flagged validator and the provenance of the flag. Timestamps are unix seconds.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlocklistEntry {
+    #[serde(deserialize_with = "identity", serialize_with = "serialize_identity")]
+    pub identity: Pubkey,
+    #[serde(default)]
+    pub added_at: Option<u64>,
+    /// The entry no longer applies from this time on. `None` never expires.
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+    /// Who flagged the validator, e.g. the name or URL of a feed.
+    #[serde(default)]
+    pub source: Option<String>,
+    /// How sure the source is, from `0.0` to `1.0`.
+    #[serde(default = "full_confidence")]
+    pub confidence: f64,
+    /// References backing the flag, e.g. signatures of sandwiched transactions.
+    #[serde(default)]
+    pub evidence: Vec<String>,
+}
+
+fn identity<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+    let key = String::deserialize(deserializer)?;
+    Pubkey::from_str(&key).map_err(|err| de::Error::custom(format!("{key}: {err}")))
+}
+
+fn serialize_identity<S: Serializer>(identity: &Pubkey, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(identity)
+}
+
+fn full_confidence() -> f64 {
+    1.0
+}
+
+impl BlocklistEntry {
+    /// Entry without provenance that never expires, with full confidence.
+    pub fn new(identity: Pubkey) -> Self {
+        Self {
+            identity,
+            added_at: None,
+            expires_at: None,
+            source: None,
+            confidence: full_confidence(),
+            evidence: vec![],
+        }
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires_at.is_some_and(|expires_at| now >= expires_at)
+    }
+
+    /// Combine the entries of two different sources for the same validator into one, e.g. to
+    /// summarize a validator's flags. [`Blocklist`] keeps the sources apart instead.
+    ///
+    /// - `confidence`, `source` and `expires_at` are taken together from the more confident
+    ///   entry, so a confidence never outlives the flag it came with,
+    /// - on a tie `source` is kept from `self` and `expires_at` is the latest of the two, where
+    ///   an entry that never expires wins,
+    /// - `added_at` is the earliest of the two,
+    /// - `evidence` is the union of both, in order of first appearance.
+    pub fn merge(&mut self, other: BlocklistEntry) {
+        debug_assert_eq!(self.identity, other.identity);
+        if other.confidence > self.confidence {
+            self.confidence = other.confidence;
+            self.source = other.source;
+            self.expires_at = other.expires_at;
+        } else if other.confidence == self.confidence {
+            self.expires_at = self.expires_at.zip(other.expires_at).map(|(a, b)| a.max(b));
+        }
+        self.added_at = match (self.added_at, other.added_at) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        for evidence in other.evidence {
+            if !self.evidence.contains(&evidence) {
+                self.evidence.push(evidence);
+            }
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if (0.0..=1.0).contains(&self.confidence) {
+            return Ok(());
+        }
+        Err(AntiSandwichError::InvalidConfig(format!(
+            "confidence {} of {} is not within 0.0..=1.0",
+            self.confidence, self.identity
+        )))
+    }
+}
+
+/// Entry or bare identity in a JSON blocklist.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonEntry {
+    Identity(#[serde(deserialize_with = "identity")] Pubkey),
+    Entry(BlocklistEntry),
+}
+
+/// Flagged validators with provenance, one entry per identity and source.
+///
+/// Entries from different sources are kept apart so that each keeps its own confidence and
+/// expiry; a validator is active while any of its entries is.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Blocklist {
+    entries: HashMap<Pubkey, Vec<BlocklistEntry>>,
+}
+
+impl Blocklist {
+    /// Parse a JSON array whose items are either validator identities or [`BlocklistEntry`]
+    /// objects. Repeated identities are added as described on [`Blocklist::insert`].
+    pub fn from_json(s: &str) -> Result<Self> {
+        let entries: Vec<JsonEntry> = serde_json::from_str(s)?;
+        Self::from_entries(entries.into_iter().map(|entry| match entry {
+            JsonEntry::Identity(identity) => BlocklistEntry::new(identity),
+            JsonEntry::Entry(entry) => entry,
+        }))
+    }
+
+    /// Blocklist of `entries`, inserted in order.
+    pub fn from_entries(entries: impl IntoIterator<Item = BlocklistEntry>) -> Result<Self> {
+        let mut blocklist = Blocklist::default();
+        entries.into_iter().try_for_each(|entry| blocklist.insert(entry))?;
+        Ok(blocklist)
+    }
+
+    /// Add `entry`, failing if its confidence is not within `0.0..=1.0`. An existing entry of
+    /// the same validator and source is replaced unless it was added later than `entry`, so a
+    /// source can lower its confidence or move the expiry of a flag.
+    pub fn insert(&mut self, entry: BlocklistEntry) -> Result<()> {
+        entry.validate()?;
+        self.add(entry);
+        Ok(())
+    }
+
+    fn add(&mut self, entry: BlocklistEntry) {
+        let entries = self.entries.entry(entry.identity).or_default();
+        match entries.iter_mut().find(|existing| existing.source == entry.source) {
+            // an undated entry never replaces a dated one
+            Some(existing) if entry.added_at >= existing.added_at => *existing = entry,
+            Some(_) => {}
+            None => entries.push(entry),
+        }
+    }
+
+    /// Combine lists from different sources. A validator on several of them keeps the entry of
+    /// each source.
+    pub fn merge(lists: impl IntoIterator<Item = Blocklist>) -> Self {
+        let mut merged = Blocklist::default();
+        for entry in lists.into_iter().flat_map(|list| list.entries.into_values().flatten()) {
+            merged.add(entry);
+        }
+        merged
+    }
+
+    /// Entries for `identity`, one per source in order of first appearance.
+    pub fn get(&self, identity: &Pubkey) -> Option<&[BlocklistEntry]> {
+        self.entries.get(identity).map(Vec::as_slice)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &BlocklistEntry> {
+        self.entries.values().flatten()
+    }
+
+    /// Number of flagged validators.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Entries that have not expired at `now` and have at least `min_confidence`.
+    pub fn active_entries(
+        &self,
+        now: u64,
+        min_confidence: f64,
+    ) -> impl Iterator<Item = &BlocklistEntry> {
+        self.entries()
+            .filter(move |entry| !entry.is_expired(now) && entry.confidence >= min_confidence)
+    }
+
+    /// Identities of the [active entries](Self::active_entries). This is the set windows are
+    /// built from.
+    pub fn active(&self, now: u64, min_confidence: f64) -> HashSet<Pubkey> {
+        self.active_entries(now, min_confidence).map(|entry| entry.identity).collect()
+    }
+}
+
+impl FromIterator<Pubkey> for Blocklist {
+    fn from_iter<I: IntoIterator<Item = Pubkey>>(identities: I) -> Self {
+        let mut blocklist = Blocklist::default();
+        identities.into_iter().for_each(|identity| blocklist.add(BlocklistEntry::new(identity)));
+        blocklist
+    }
+}
+
+/// Current unix time in seconds, the `now` blocklist entries are checked against.
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(identity: Pubkey, source: &str, confidence: f64) -> BlocklistEntry {
+        BlocklistEntry {
+            source: Some(source.to_string()),
+            confidence,
+            ..BlocklistEntry::new(identity)
+        }
+    }
+
+    #[test]
+    fn filters_expired_and_unconfident() {
+        let [a, b, c] = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+        let blocklist = Blocklist::from_json(&format!(
+            r#"["{a}",
+                {{"identity": "{b}", "added_at": 100, "expires_at": 200, "source": "feed",
+                  "confidence": 0.9, "evidence": ["5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnb"]}},
+                {{"identity": "{c}", "confidence": 0.4}}]"#
+        ))
+        .unwrap();
+
+        assert_eq!(blocklist.len(), 3);
+        assert_eq!(blocklist.get(&a), Some(&[BlocklistEntry::new(a)][..]));
+        assert_eq!(blocklist.get(&b).unwrap()[0].source.as_deref(), Some("feed"));
+        assert_eq!(blocklist.active(199, 0.0), HashSet::from([a, b, c]));
+        assert_eq!(blocklist.active(200, 0.0), HashSet::from([a, c]));
+        assert_eq!(blocklist.active(199, 0.5), HashSet::from([a, b]));
+
+        let invalid = format!(r#"[{{"identity": "{a}", "confidence": 1.5}}]"#);
+        assert!(matches!(Blocklist::from_json(&invalid), Err(AntiSandwichError::InvalidConfig(_))));
+        let unknown = format!(r#"[{{"identity": "{a}", "weight": 1}}]"#);
+        assert!(Blocklist::from_json(&unknown).is_err());
+    }
+
+    #[test]
+    fn merges_sources() {
+        let validator = Pubkey::new_unique();
+        let feed = BlocklistEntry {
+            added_at: Some(200),
+            expires_at: Some(1_000),
+            evidence: vec!["tx1".to_string(), "tx2".to_string()],
+            ..entry(validator, "feed", 0.6)
+        };
+        let desk = BlocklistEntry {
+            added_at: Some(100),
+            expires_at: Some(500),
+            evidence: vec!["tx2".to_string(), "tx3".to_string()],
+            ..entry(validator, "desk", 0.8)
+        };
+        let other = Pubkey::new_unique();
+
+        let merged = Blocklist::merge([
+            Blocklist::from_entries([feed.clone()]).unwrap(),
+            Blocklist::from_entries([desk.clone(), entry(other, "desk", 0.5)]).unwrap(),
+        ]);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged.get(&validator), Some(&[feed.clone(), desk.clone()][..]));
+
+        // the confident desk flag expires first and must not lend its confidence to the feed's
+        assert_eq!(merged.active(400, 0.7), HashSet::from([validator]));
+        assert!(!merged.active(700, 0.7).contains(&validator));
+        assert!(merged.active(700, 0.6).contains(&validator));
+        assert_eq!(merged.active_entries(700, 0.6).collect::<Vec<_>>(), [&feed]);
+
+        // a later report of the same source replaces the earlier one, even when less confident
+        let downgrade = BlocklistEntry {
+            added_at: Some(300),
+            expires_at: Some(2_000),
+            ..entry(validator, "desk", 0.2)
+        };
+        let outdated = BlocklistEntry { added_at: Some(50), ..entry(validator, "desk", 0.9) };
+        let merged = Blocklist::from_entries([desk.clone(), downgrade.clone(), outdated]).unwrap();
+        assert_eq!(merged.get(&validator), Some(&[downgrade][..]));
+        assert!(merged.active(400, 0.7).is_empty());
+        assert_eq!(merged.active(400, 0.2), HashSet::from([validator]));
+
+        // an undated report only replaces an undated one
+        let undated = BlocklistEntry { added_at: None, ..entry(validator, "desk", 0.9) };
+        let merged = Blocklist::from_entries([desk.clone(), undated.clone()]).unwrap();
+        assert_eq!(merged.get(&validator), Some(&[desk][..]));
+        let manual = BlocklistEntry { confidence: 0.4, ..undated.clone() };
+        let merged = Blocklist::from_entries([undated, manual.clone()]).unwrap();
+        assert_eq!(merged.get(&validator), Some(&[manual][..]));
+
+        // combining sources into one entry keeps the confident flag with its own expiry
+        let mut summary = feed;
+        summary.merge(entry(validator, "desk", 0.8));
+        assert_eq!(summary.source.as_deref(), Some("desk"));
+        assert_eq!((summary.confidence, summary.expires_at), (0.8, None));
+        assert_eq!(summary.evidence, ["tx1", "tx2"]);
+    }
+
+    #[test]
+    fn validates_confidence() {
+        let validator = Pubkey::new_unique();
+        let mut blocklist = Blocklist::default();
+        for confidence in [f64::NAN, 1.5, -0.1] {
+            let err = blocklist.insert(entry(validator, "feed", confidence)).unwrap_err();
+            assert!(matches!(err, AntiSandwichError::InvalidConfig(_)), "{confidence}");
+        }
+        assert!(blocklist.is_empty());
+        assert!(Blocklist::from_entries([entry(validator, "feed", f64::INFINITY)]).is_err());
+        blocklist.insert(entry(validator, "feed", 0.0)).unwrap();
+        assert_eq!(blocklist.len(), 1);
+    }
+}
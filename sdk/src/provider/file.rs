@@ -0,0 +1,137 @@
+use super::{Blocklist, BlocklistSource, LeaderSchedule, LeaderScheduleSource, SlotSource};
+use crate::{AntiSandwichError, Result};
+use serde::Deserialize;
+use solana_pubkey::Pubkey;
+use std::{collections::HashMap, fs, path::PathBuf, str::FromStr, sync::Arc};
+
+/// One epoch of a leader schedule file. `schedule` uses the `getLeaderSchedule` layout.
+#[derive(Deserialize)]
+struct EpochEntry {
+    epoch: u64,
+    first_slot: u64,
+    slots_in_epoch: u64,
+    schedule: HashMap<String, Vec<u64>>,
+}
+
+/// Leader schedules read from a JSON file holding an array of
+/// `{"epoch", "first_slot", "slots_in_epoch", "schedule"}` objects.
+///
+/// The file is re-read on every call so it can be refreshed by an external process.
+pub struct FileLeaderSchedule {
+    path: PathBuf,
+}
+
+impl FileLeaderSchedule {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl LeaderScheduleSource for FileLeaderSchedule {
+    fn leader_schedule(&self, slot: u64) -> Result<Arc<LeaderSchedule>> {
+        let entries: Vec<EpochEntry> = serde_json::from_str(&fs::read_to_string(&self.path)?)?;
+        let entry = entries
+            .iter()
+            .find(|e| (e.first_slot..e.first_slot + e.slots_in_epoch).contains(&slot))
+            .ok_or_else(|| {
+                AntiSandwichError::Provider(format!(
+                    "{} has no schedule for slot {slot}",
+                    self.path.display()
+                ))
+            })?;
+
+        LeaderSchedule::from_identity_map(
+            entry.epoch,
+            entry.first_slot,
+            entry.slots_in_epoch,
+            &entry.schedule,
+        )
+        .map(Arc::new)
+    }
+}
+
+/// Slot read from a file that holds a single integer.
+pub struct FileSlot {
+    path: PathBuf,
+}
+
+impl FileSlot {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl SlotSource for FileSlot {
+    fn slot(&self) -> Result<u64> {
+        Ok(fs::read_to_string(&self.path)?.trim().parse()?)
+    }
+}
+
+/// Blocklist read from a text file with one validator identity per line, or from a JSON file in
+/// the format of [`Blocklist::from_json`] if the file starts with `[`.
+/// In text files, blank lines and lines starting with `#` are ignored.
+pub struct FileBlocklist {
+    path: PathBuf,
+}
+
+impl FileBlocklist {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl BlocklistSource for FileBlocklist {
+    fn blocklist(&self) -> Result<Blocklist> {
+        let contents = fs::read_to_string(&self.path)?;
+        if contents.trim_start().starts_with('[') {
+            return Blocklist::from_json(&contents);
+        }
+        contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| Ok(Pubkey::from_str(line)?))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_files() {
+        let dir = std::env::temp_dir().join(format!("anti-sandwich-file-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let leader = Pubkey::new_unique();
+
+        let schedule = dir.join("schedule.json");
+        fs::write(
+            &schedule,
+            format!(
+                r#"[{{"epoch": 3, "first_slot": 96, "slots_in_epoch": 4, "schedule": {{"{leader}": [0, 1, 2, 3]}}}}]"#
+            ),
+        )
+        .unwrap();
+        let blocklist = dir.join("blocklist.txt");
+        fs::write(&blocklist, format!("# flagged\n\n{leader}\n")).unwrap();
+        let slot = dir.join("slot");
+        fs::write(&slot, "97\n").unwrap();
+
+        let schedule = FileLeaderSchedule::new(&schedule).leader_schedule(97).unwrap();
+        assert_eq!(schedule.epoch, 3);
+        assert_eq!(schedule.leader_at(99), Some(&leader));
+        assert!(FileLeaderSchedule::new(dir.join("schedule.json")).leader_schedule(100).is_err());
+        assert_eq!(
+            FileBlocklist::new(&blocklist).blocklist().unwrap(),
+            Blocklist::from_iter([leader])
+        );
+        fs::write(&blocklist, format!(r#"[{{"identity": "{leader}", "expires_at": 100}}]"#))
+            .unwrap();
+        let entries = FileBlocklist::new(&blocklist).blocklist().unwrap();
+        assert_eq!(entries.get(&leader).unwrap()[0].expires_at, Some(100));
+        assert_eq!(FileSlot::new(&slot).slot().unwrap(), 97);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,99 @@
+use super::{
+    Blocklist, BlocklistEntry, BlocklistSource, LeaderSchedule, LeaderScheduleSource, SlotSource,
+};
+use crate::{AntiSandwichError, Result};
+use solana_pubkey::Pubkey;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, RwLock,
+};
+
+/// Leader schedules held in memory, one per epoch.
+#[derive(Default)]
+pub struct InMemoryLeaderSchedule {
+    schedules: RwLock<Vec<Arc<LeaderSchedule>>>,
+}
+
+impl InMemoryLeaderSchedule {
+    pub fn new(schedules: Vec<LeaderSchedule>) -> Self {
+        Self { schedules: RwLock::new(schedules.into_iter().map(Arc::new).collect()) }
+    }
+
+    /// Add (or replace) the schedule of `schedule.epoch`.
+    pub fn insert(&self, schedule: LeaderSchedule) {
+        let mut schedules = self.schedules.write().expect("lock poisoned");
+        schedules.retain(|s| s.epoch != schedule.epoch);
+        schedules.push(Arc::new(schedule));
+    }
+}
+
+impl LeaderScheduleSource for InMemoryLeaderSchedule {
+    fn leader_schedule(&self, slot: u64) -> Result<Arc<LeaderSchedule>> {
+        self.schedules
+            .read()
+            .expect("lock poisoned")
+            .iter()
+            .find(|s| s.contains(slot))
+            .cloned()
+            .ok_or_else(|| {
+                AntiSandwichError::Provider(format!("no leader schedule loaded for slot {slot}"))
+            })
+    }
+}
+
+/// Slot held in memory. Can be moved forward by the owner, e.g. from a test or a slot
+/// subscription.
+#[derive(Default)]
+pub struct InMemorySlot {
+    slot: AtomicU64,
+}
+
+impl InMemorySlot {
+    pub fn new(slot: u64) -> Self {
+        Self { slot: AtomicU64::new(slot) }
+    }
+
+    pub fn set(&self, slot: u64) {
+        self.slot.store(slot, Ordering::Relaxed);
+    }
+}
+
+impl SlotSource for InMemorySlot {
+    fn slot(&self) -> Result<u64> {
+        Ok(self.slot.load(Ordering::Relaxed))
+    }
+}
+
+/// Blocklist held in memory.
+#[derive(Default)]
+pub struct InMemoryBlocklist {
+    validators: RwLock<Blocklist>,
+}
+
+impl InMemoryBlocklist {
+    pub fn new(validators: impl IntoIterator<Item = Pubkey>) -> Self {
+        Self::from(validators.into_iter().collect::<Blocklist>())
+    }
+
+    pub fn replace(&self, validators: impl IntoIterator<Item = Pubkey>) {
+        *self.validators.write().expect("lock poisoned") = validators.into_iter().collect();
+    }
+
+    /// Replace the blocklist with `entries`, keeping the current one if an entry is invalid.
+    pub fn replace_entries(&self, entries: impl IntoIterator<Item = BlocklistEntry>) -> Result<()> {
+        *self.validators.write().expect("lock poisoned") = Blocklist::from_entries(entries)?;
+        Ok(())
+    }
+}
+
+impl From<Blocklist> for InMemoryBlocklist {
+    fn from(blocklist: Blocklist) -> Self {
+        Self { validators: RwLock::new(blocklist) }
+    }
+}
+
+impl BlocklistSource for InMemoryBlocklist {
+    fn blocklist(&self) -> Result<Blocklist> {
+        Ok(self.validators.read().expect("lock poisoned").clone())
+    }
+}
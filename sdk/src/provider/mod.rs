@@ -0,0 +1,350 @@
+//! Pluggable sources for the data a `NefariousWindow` is built from: the leader schedule, the
+//! current slot and the validator blocklist.
+//!
+//! Every source comes in three flavours: static files, in-memory data (tests, caches) and a
+//! JSON-RPC / HTTP client. [`ProviderWindow`] combines any three of them into a
+//! [`WindowSource`], so the same window-building code runs in tests and in production.
+
+mod blocklist;
+mod file;
+mod memory;
+#[cfg(feature = "rpc")]
+mod rpc;
+
+pub(crate) use blocklist::unix_now;
+pub use blocklist::{Blocklist, BlocklistEntry};
+pub use file::{FileBlocklist, FileLeaderSchedule, FileSlot};
+pub use memory::{InMemoryBlocklist, InMemoryLeaderSchedule, InMemorySlot};
+#[cfg(feature = "rpc")]
+pub use rpc::{HttpBlocklist, RpcProvider};
+
+use crate::{build_window_at, AntiSandwichError, Result, WindowSource};
+use anti_sandwich_common::NefariousWindow;
+use solana_pubkey::Pubkey;
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::Arc,
+};
+
+/// Slots per leader rotation.
+pub const SLOTS_PER_LEADER: u64 = 4;
+
+/// Leader schedule for a single epoch, one entry per slot.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LeaderSchedule {
+    pub epoch: u64,
+    pub first_slot: u64,
+    pub slot_leaders: Vec<Pubkey>,
+}
+
+impl LeaderSchedule {
+    /// Build a schedule from the `getLeaderSchedule` layout: validator identity → slot indices
+    /// relative to `first_slot`.
+    pub fn from_identity_map(
+        epoch: u64,
+        first_slot: u64,
+        slots_in_epoch: u64,
+        schedule: &HashMap<String, Vec<u64>>,
+    ) -> Result<Self> {
+        let mut slot_leaders = vec![None; slots_in_epoch as usize];
+        for (identity, indices) in schedule {
+            let identity = Pubkey::from_str(identity)?;
+            for &index in indices {
+                let entry = slot_leaders.get_mut(index as usize).ok_or_else(|| {
+                    AntiSandwichError::Provider(format!(
+                        "slot index {index} is outside epoch {epoch} ({slots_in_epoch} slots)"
+                    ))
+                })?;
+                *entry = Some(identity);
+            }
+        }
+
+        let slot_leaders = slot_leaders
+            .into_iter()
+            .enumerate()
+            .map(|(index, leader)| {
+                leader.ok_or_else(|| {
+                    AntiSandwichError::Provider(format!(
+                        "epoch {epoch} has no leader for index {index}"
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { epoch, first_slot, slot_leaders })
+    }
+
+    /// Last slot (inclusive) covered by this schedule.
+    pub fn last_slot(&self) -> u64 {
+        (self.first_slot + self.slot_leaders.len() as u64).saturating_sub(1)
+    }
+
+    pub fn contains(&self, slot: u64) -> bool {
+        !self.slot_leaders.is_empty() && (self.first_slot..=self.last_slot()).contains(&slot)
+    }
+
+    pub fn leader_at(&self, slot: u64) -> Option<&Pubkey> {
+        if !self.contains(slot) {
+            return None;
+        }
+        self.slot_leaders.get((slot - self.first_slot) as usize)
+    }
+
+    /// First slot of the leader rotation that contains `slot`.
+    pub fn leader_start(&self, slot: u64) -> u64 {
+        slot - slot.saturating_sub(self.first_slot) % SLOTS_PER_LEADER
+    }
+}
+
+/// Source of the leader schedule.
+pub trait LeaderScheduleSource {
+    /// Leader schedule of the epoch that contains `slot`.
+    fn leader_schedule(&self, slot: u64) -> Result<Arc<LeaderSchedule>>;
+}
+
+/// Source of the slot the cluster is currently processing.
+pub trait SlotSource {
+    fn slot(&self) -> Result<u64>;
+}
+
+/// Source of the validators that are considered nefarious.
+pub trait BlocklistSource {
+    fn blocklist(&self) -> Result<Blocklist>;
+}
+
+macro_rules! forward_source {
+    ($trait:ident, $method:ident, $ret:ty $(, $arg:ident: $ty:ty)*) => {
+        impl<T: $trait + ?Sized> $trait for &T {
+            fn $method(&self $(, $arg: $ty)*) -> Result<$ret> {
+                (**self).$method($($arg),*)
+            }
+        }
+
+        impl<T: $trait + ?Sized> $trait for Arc<T> {
+            fn $method(&self $(, $arg: $ty)*) -> Result<$ret> {
+                (**self).$method($($arg),*)
+            }
+        }
+    };
+}
+
+forward_source!(LeaderScheduleSource, leader_schedule, Arc<LeaderSchedule>, slot: u64);
+forward_source!(SlotSource, slot, u64);
+forward_source!(BlocklistSource, blocklist, Blocklist);
+
+/// Slots in `[window_start, window_start + 191]` whose leader is on the `blocklist`.
+///
+/// The window may straddle an epoch boundary, in which case the schedule of the next epoch is
+/// fetched as well.
+pub fn nefarious_leader_slots<L: LeaderScheduleSource + ?Sized>(
+    leader_schedule: &L,
+    blocklist: &HashSet<Pubkey>,
+    window_start: u64,
+) -> Result<Vec<u64>> {
+    let window_end = window_start + NefariousWindow::MAX_LEADERS as u64 * SLOTS_PER_LEADER - 1;
+    let mut slots = Vec::new();
+    let mut slot = window_start;
+
+    while slot <= window_end {
+        let schedule = leader_schedule.leader_schedule(slot)?;
+        if !schedule.contains(slot) {
+            return Err(AntiSandwichError::Provider(format!(
+                "leader schedule for epoch {} misses slot {slot}",
+                schedule.epoch
+            )));
+        }
+        let last = schedule.last_slot().min(window_end);
+        for s in slot..=last {
+            if schedule.leader_at(s).is_some_and(|leader| blocklist.contains(leader)) {
+                slots.push(s);
+            }
+        }
+        slot = last + 1;
+    }
+
+    Ok(slots)
+}
+
+/// [`WindowSource`] backed by a leader schedule, a slot and a blocklist source.
+///
+/// The window is anchored at the start of the leader rotation the current slot belongs to, so
+/// each bit of the window lines up with exactly one leader. Expired blocklist entries and entries
+/// below `min_confidence` are left out.
+pub struct ProviderWindow<L, S, B> {
+    pub leader_schedule: L,
+    pub slot: S,
+    pub blocklist: B,
+    pub min_confidence: f64,
+}
+
+impl<L, S, B> ProviderWindow<L, S, B>
+where
+    L: LeaderScheduleSource,
+    S: SlotSource,
+    B: BlocklistSource,
+{
+    pub fn new(leader_schedule: L, slot: S, blocklist: B) -> Self {
+        Self { leader_schedule, slot, blocklist, min_confidence: 0.0 }
+    }
+
+    pub fn with_min_confidence(mut self, min_confidence: f64) -> Self {
+        self.min_confidence = min_confidence;
+        self
+    }
+}
+
+impl<L, S, B> WindowSource for ProviderWindow<L, S, B>
+where
+    L: LeaderScheduleSource,
+    S: SlotSource,
+    B: BlocklistSource,
+{
+    fn window(&self) -> Result<NefariousWindow> {
+        let slot = self.slot.slot()?;
+        let blocklist = self.blocklist.blocklist()?;
+        window_at(&self.leader_schedule, slot, &blocklist, self.min_confidence)
+    }
+}
+
+/// Window anchored at the start of the leader rotation `slot` belongs to, flagging the leaders
+/// whose blocklist entries are active now with `min_confidence`. Shared by [`ProviderWindow`]
+/// and the window services, which keep their data cached.
+pub(crate) fn window_at<L: LeaderScheduleSource + ?Sized>(
+    leader_schedule: &L,
+    slot: u64,
+    blocklist: &Blocklist,
+    min_confidence: f64,
+) -> Result<NefariousWindow> {
+    let window_start = leader_schedule.leader_schedule(slot)?.leader_start(slot);
+    // expiry is checked on every window, not only when the blocklist is refreshed
+    let blocklist = blocklist.active(unix_now(), min_confidence);
+    let slots = nefarious_leader_slots(leader_schedule, &blocklist, window_start)?;
+    build_window_at(window_start, &slots)
+}
+
+/// Leader schedules of the current and the next epoch, as kept in memory by the window services.
+#[cfg(feature = "tokio")]
+#[derive(Clone, Default)]
+pub(crate) struct CachedSchedules(Vec<Arc<LeaderSchedule>>);
+
+#[cfg(feature = "tokio")]
+impl CachedSchedules {
+    /// Make sure the schedules of the epoch of `slot` and of the next epoch are cached and drop
+    /// older epochs.
+    pub(crate) fn refresh<L: LeaderScheduleSource + ?Sized>(
+        &mut self,
+        source: &L,
+        slot: u64,
+    ) -> Result<()> {
+        self.0.retain(|s| s.last_slot() >= slot);
+        if !self.0.iter().any(|s| s.contains(slot)) {
+            self.0.push(source.leader_schedule(slot)?);
+        }
+        let last_slot = self.0.iter().map(|s| s.last_slot()).max().expect("not empty");
+        if self.0.len() < 2 {
+            self.0.push(source.leader_schedule(last_slot + 1)?);
+        }
+        self.0.sort_by_key(|s| s.first_slot);
+        Ok(())
+    }
+
+    pub(crate) fn epochs(&self) -> Vec<u64> {
+        self.0.iter().map(|s| s.epoch).collect()
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl LeaderScheduleSource for CachedSchedules {
+    fn leader_schedule(&self, slot: u64) -> Result<Arc<LeaderSchedule>> {
+        self.0.iter().find(|s| s.contains(slot)).cloned().ok_or_else(|| {
+            AntiSandwichError::Provider(format!("leader schedule for slot {slot} is not cached"))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(epoch: u64, first_slot: u64, leaders: &[Pubkey], slots: usize) -> LeaderSchedule {
+        let slot_leaders = (0..slots).map(|i| leaders[(i / 4) % leaders.len()]).collect();
+        LeaderSchedule { epoch, first_slot, slot_leaders }
+    }
+
+    #[test]
+    fn identity_map_round_trip() {
+        let a = Pubkey::new_unique();
+        let b = Pubkey::new_unique();
+        let map =
+            HashMap::from([(a.to_string(), vec![0, 1, 2, 3]), (b.to_string(), vec![4, 5, 6, 7])]);
+        let schedule = LeaderSchedule::from_identity_map(10, 4_320_000, 8, &map).unwrap();
+
+        assert_eq!(schedule.leader_at(4_320_000), Some(&a));
+        assert_eq!(schedule.leader_at(4_320_007), Some(&b));
+        assert_eq!(schedule.leader_at(4_320_008), None);
+
+        let missing = HashMap::from([(a.to_string(), vec![0, 1, 2, 3])]);
+        assert!(LeaderSchedule::from_identity_map(10, 4_320_000, 8, &missing).is_err());
+    }
+
+    #[test]
+    fn provider_window_is_anchored_and_aligned() {
+        let good = Pubkey::new_unique();
+        let bad = Pubkey::new_unique();
+        let leaders = InMemoryLeaderSchedule::new(vec![schedule(0, 0, &[good, bad], 1_000)]);
+        let slot = InMemorySlot::new(402);
+        let blocklist = Arc::new(InMemoryBlocklist::new([bad]));
+        let providers = ProviderWindow::new(leaders, &slot, blocklist);
+
+        let window = providers.window().unwrap();
+        assert_eq!(window.window_start, 400);
+        for slot in window.valid_land_range() {
+            let expected = (slot / 4) % 2 == 1;
+            assert_eq!(window.is_nefarious(slot), expected, "slot {slot}");
+        }
+
+        slot.set(406);
+        assert_eq!(providers.window().unwrap().window_start, 404);
+    }
+
+    #[test]
+    fn provider_window_filters_blocklist() {
+        let [good, expired, unsure] =
+            [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+        let leaders =
+            InMemoryLeaderSchedule::new(vec![schedule(0, 0, &[good, expired, unsure], 1_000)]);
+        let blocklist = InMemoryBlocklist::default();
+        blocklist
+            .replace_entries([
+                BlocklistEntry { expires_at: Some(1), ..BlocklistEntry::new(expired) },
+                BlocklistEntry { confidence: 0.5, ..BlocklistEntry::new(unsure) },
+            ])
+            .unwrap();
+        let providers = ProviderWindow::new(leaders, InMemorySlot::new(0), blocklist);
+
+        // leaders rotate good, expired, unsure every 4 slots
+        let window = providers.window().unwrap();
+        assert!(!window.is_nefarious(4));
+        assert!(window.is_nefarious(8));
+
+        let providers = providers.with_min_confidence(0.8);
+        assert_eq!(providers.window().unwrap().nefarious, [0; 6]);
+    }
+
+    #[test]
+    fn window_spans_epoch_boundary() {
+        let good = Pubkey::new_unique();
+        let bad = Pubkey::new_unique();
+        let leaders = InMemoryLeaderSchedule::new(vec![
+            schedule(0, 0, &[good], 100),
+            schedule(1, 100, &[bad], 100),
+        ]);
+
+        let slots = nefarious_leader_slots(&leaders, &HashSet::from([bad]), 8).unwrap();
+        assert_eq!(slots, (100..=199).collect::<Vec<_>>());
+
+        // no schedule for epoch 2
+        assert!(nefarious_leader_slots(&leaders, &HashSet::from([bad]), 120).is_err());
+    }
+}
@@ -0,0 +1,233 @@
+use super::{Blocklist, BlocklistSource, LeaderSchedule, LeaderScheduleSource, SlotSource};
+use crate::{AntiSandwichError, Result};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use solana_epoch_schedule::EpochSchedule;
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RpcEpochSchedule {
+    slots_per_epoch: u64,
+    leader_schedule_slot_offset: u64,
+    warmup: bool,
+    first_normal_epoch: u64,
+    first_normal_slot: u64,
+}
+
+/// Leader schedule and slot source backed by a Solana JSON-RPC endpoint.
+///
+/// Uses `getSlot`, `getEpochSchedule` and `getLeaderSchedule`. The epoch schedule is fetched
+/// once and cached, as it never changes for a cluster.
+pub struct RpcProvider {
+    url: String,
+    commitment: &'static str,
+    agent: ureq::Agent,
+    epoch_schedule: OnceLock<EpochSchedule>,
+}
+
+impl RpcProvider {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            commitment: "processed",
+            agent: ureq::AgentBuilder::new().timeout(TIMEOUT).build(),
+            epoch_schedule: OnceLock::new(),
+        }
+    }
+
+    /// Commitment used for `getSlot` and `getLeaderSchedule` (default `processed`).
+    pub fn with_commitment(mut self, commitment: &'static str) -> Self {
+        self.commitment = commitment;
+        self
+    }
+
+    fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
+        let body = json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params});
+        let response: RpcResponse<T> = self.agent.post(&self.url).send_json(body)?.into_json()?;
+
+        match (response.result, response.error) {
+            (_, Some(err)) => Err(AntiSandwichError::Provider(format!(
+                "{method} failed ({}): {}",
+                err.code, err.message
+            ))),
+            (Some(result), None) => Ok(result),
+            (None, None) => {
+                Err(AntiSandwichError::Provider(format!("{method} returned no result")))
+            }
+        }
+    }
+
+    pub fn epoch_schedule(&self) -> Result<EpochSchedule> {
+        if let Some(schedule) = self.epoch_schedule.get() {
+            return Ok(schedule.clone());
+        }
+        let s: RpcEpochSchedule = self.call("getEpochSchedule", json!([]))?;
+        let schedule = EpochSchedule {
+            slots_per_epoch: s.slots_per_epoch,
+            leader_schedule_slot_offset: s.leader_schedule_slot_offset,
+            warmup: s.warmup,
+            first_normal_epoch: s.first_normal_epoch,
+            first_normal_slot: s.first_normal_slot,
+        };
+        Ok(self.epoch_schedule.get_or_init(|| schedule).clone())
+    }
+}
+
+impl LeaderScheduleSource for RpcProvider {
+    fn leader_schedule(&self, slot: u64) -> Result<Arc<LeaderSchedule>> {
+        let epoch_schedule = self.epoch_schedule()?;
+        let epoch = epoch_schedule.get_epoch(slot);
+        let first_slot = epoch_schedule.get_first_slot_in_epoch(epoch);
+
+        let schedule: Option<HashMap<String, Vec<u64>>> =
+            self.call("getLeaderSchedule", json!([slot, {"commitment": self.commitment}]))?;
+        let schedule = schedule.ok_or_else(|| {
+            AntiSandwichError::Provider(format!(
+                "leader schedule for epoch {epoch} is not available"
+            ))
+        })?;
+
+        LeaderSchedule::from_identity_map(
+            epoch,
+            first_slot,
+            epoch_schedule.get_slots_in_epoch(epoch),
+            &schedule,
+        )
+        .map(Arc::new)
+    }
+}
+
+impl SlotSource for RpcProvider {
+    fn slot(&self) -> Result<u64> {
+        self.call("getSlot", json!([{"commitment": self.commitment}]))
+    }
+}
+
+/// Blocklist fetched over HTTP from an endpoint returning a JSON array of validator identities or
+/// entries, as parsed by [`Blocklist::from_json`].
+pub struct HttpBlocklist {
+    url: String,
+    agent: ureq::Agent,
+}
+
+impl HttpBlocklist {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self { url: url.into(), agent: ureq::AgentBuilder::new().timeout(TIMEOUT).build() }
+    }
+}
+
+impl BlocklistSource for HttpBlocklist {
+    fn blocklist(&self) -> Result<Blocklist> {
+        Blocklist::from_json(&self.agent.get(&self.url).call()?.into_string()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_pubkey::Pubkey;
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        str::FromStr,
+        thread,
+    };
+
+    /// Minimal HTTP server answering every request with `respond(body)`.
+    fn mock_server(respond: fn(&str) -> String) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" || line.is_empty() {
+                        break;
+                    }
+                    if let Some(len) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                        content_length = len.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+
+                let reply = respond(&String::from_utf8(body).unwrap());
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{reply}",
+                    reply.len()
+                )
+                .unwrap();
+            }
+        });
+
+        url
+    }
+
+    const LEADER: &str = "BfXm7pxBsqF5BpZqKSeNLzBUHXbnvase19ge2XHofhb3";
+
+    fn rpc(body: &str) -> String {
+        let request: Value = serde_json::from_str(body).unwrap();
+        let result = match request["method"].as_str().unwrap() {
+            "getSlot" => json!(1_000_005),
+            "getEpochSchedule" => json!({
+                "slotsPerEpoch": 8, "leaderScheduleSlotOffset": 8, "warmup": false,
+                "firstNormalEpoch": 0, "firstNormalSlot": 0
+            }),
+            "getLeaderSchedule" => json!({ LEADER: [0, 1, 2, 3, 4, 5, 6, 7] }),
+            _ => {
+                let error = json!({"code": -32601, "message": "Method not found"});
+                return json!({"jsonrpc": "2.0", "id": 1, "error": error}).to_string();
+            }
+        };
+        json!({"jsonrpc": "2.0", "id": 1, "result": result}).to_string()
+    }
+
+    #[test]
+    fn rpc_provider() {
+        let provider = RpcProvider::new(mock_server(rpc));
+
+        assert_eq!(provider.slot().unwrap(), 1_000_005);
+
+        let schedule = provider.leader_schedule(1_000_005).unwrap();
+        assert_eq!(schedule.epoch, 125_000);
+        assert_eq!(schedule.first_slot, 1_000_000);
+        assert_eq!(schedule.leader_at(1_000_007), Some(&Pubkey::from_str(LEADER).unwrap()));
+
+        let err = provider.call::<u64>("getBalance", json!([])).unwrap_err();
+        assert!(err.to_string().contains("Method not found"));
+    }
+
+    #[test]
+    fn http_blocklist() {
+        let blocklist = HttpBlocklist::new(mock_server(|_| json!([LEADER]).to_string()));
+        assert_eq!(
+            blocklist.blocklist().unwrap(),
+            Blocklist::from_iter([Pubkey::from_str(LEADER).unwrap()])
+        );
+    }
+}
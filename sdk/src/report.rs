@@ -0,0 +1,47 @@
+//! Decoding the result of [`report_if_nefarious`](crate::report_if_nefarious).
+//!
+//! The program answers with a single return-data byte. Off-chain clients read it from the
+//! `returnData` of a `simulateTransaction` response to preflight whether a transaction would
+//! land on a flagged leader; on-chain callers read it with `get_return_data` after the CPI.
+
+pub use anti_sandwich_common::ReportResult;
+
+use crate::AntiSandwichClient;
+use solana_pubkey::Pubkey;
+
+/// Decode return data set by `program_id`. `None` if the data was not set by the upstream
+/// anti-sandwich program (e.g. a later instruction overwrote it) or is malformed. Use
+/// [`AntiSandwichClient::parse_report`] for other deployments.
+pub fn parse_report(program_id: &Pubkey, data: &[u8]) -> Option<ReportResult> {
+    AntiSandwichClient::default().parse_report(program_id, data)
+}
+
+/// Decode the `returnData` of a `simulateTransaction` response, where `program_id` is base58
+/// and `data` is base64 encoded.
+pub fn parse_simulated_report(program_id: &str, data: &str) -> Option<ReportResult> {
+    AntiSandwichClient::default().parse_simulated_report(program_id, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{report_if_nefarious, PROGRAM_ID, REPORT_DISC};
+    use anti_sandwich_common::NefariousWindow;
+
+    #[test]
+    fn builds_and_parses() {
+        let window = NefariousWindow { window_start: 350_000_000, nefarious: [1, 0, 0, 0, 0, 0] };
+        let ix = report_if_nefarious(window);
+        assert_eq!(ix.program_id, PROGRAM_ID);
+        assert!(ix.accounts.is_empty());
+        assert_eq!(ix.data[0], REPORT_DISC);
+        assert_eq!(NefariousWindow::unpack(&ix.data[1..]), Some(window));
+
+        let program_id = PROGRAM_ID.to_string();
+        assert_eq!(parse_simulated_report(&program_id, "AQ=="), Some(ReportResult::Nefarious));
+        assert_eq!(parse_simulated_report(&program_id, "AA=="), Some(ReportResult::NotNefarious));
+        assert_eq!(parse_simulated_report(&program_id, "Ag=="), Some(ReportResult::Error));
+        assert_eq!(parse_simulated_report(&program_id, ""), None);
+        assert_eq!(parse_report(&Pubkey::new_unique(), &[1]), None);
+    }
+}
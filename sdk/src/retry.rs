@@ -0,0 +1,232 @@
+//! Rebuild and resubmit after a guard abort.
+//!
+//! A transaction can still reach a flagged leader, e.g. when it lands later than the
+//! [`Scheduler`] planned, and then fails with [`ErrorCode::NefariousLeader`]. [`RetryController`]
+//! recognizes that failure, fetches a fresh window and blockhash, rebuilds the transaction with
+//! the caller's factory and schedules it for the next safe leader run again.
+//!
+//! [`ErrorCode::NefariousLeader`]: crate::ErrorCode::NefariousLeader
+
+use crate::{
+    outcome::TransactionFailure,
+    scheduler::{Clock, Scheduler, Submission, TransactionSender},
+    AntiSandwichClient, AntiSandwichError, Result, WindowSource,
+};
+use anti_sandwich_common::NefariousWindow;
+use solana_hash::Hash;
+use solana_message::VersionedMessage;
+use solana_signature::Signature;
+use solana_signer::Signer;
+use solana_transaction::versioned::VersionedTransaction;
+use solana_transaction_error::TransactionError;
+use std::{future::Future, time::Duration};
+
+/// A [`TransactionSender`] that can also wait for the result of a sent transaction.
+pub trait ConfirmingSender: TransactionSender {
+    /// Wait until `signature` lands. `Ok(None)` if it succeeded, `Ok(Some(err))` if it failed.
+    fn confirm(
+        &self,
+        signature: &Signature,
+    ) -> impl Future<Output = Result<Option<TransactionError>>> + Send;
+}
+
+#[derive(Clone, Debug)]
+pub struct RetryConfig {
+    /// Submissions in total, the first one included. At least 1.
+    pub max_attempts: u32,
+    /// Wait after the first abort, doubled after each further one.
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            backoff: Duration::from_millis(400),
+            max_backoff: Duration::from_secs(4),
+        }
+    }
+}
+
+/// Result of [`RetryController::submit`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Landed {
+    pub submission: Submission,
+    /// Number of submissions it took.
+    pub attempts: u32,
+}
+
+pub struct RetryController<W, C, T> {
+    client: AntiSandwichClient,
+    scheduler: Scheduler<W, C, T>,
+    config: RetryConfig,
+}
+
+impl<W, C, T> RetryController<W, C, T>
+where
+    W: WindowSource,
+    C: Clock,
+    T: ConfirmingSender,
+{
+    /// Retry aborts of the upstream program's guards. Fails with
+    /// [`AntiSandwichError::InvalidConfig`] if `max_attempts` is 0.
+    pub fn new(scheduler: Scheduler<W, C, T>, config: RetryConfig) -> Result<Self> {
+        if config.max_attempts == 0 {
+            return Err(AntiSandwichError::InvalidConfig(
+                "max_attempts must be at least 1".to_string(),
+            ));
+        }
+        Ok(Self { client: AntiSandwichClient::default(), scheduler, config })
+    }
+
+    /// Recognize aborts of another deployment than the upstream program.
+    pub fn with_client(mut self, client: AntiSandwichClient) -> Self {
+        self.client = client;
+        self
+    }
+
+    fn backoff(&self, aborts: u32) -> Duration {
+        self.config
+            .backoff
+            .saturating_mul(2u32.saturating_pow(aborts.saturating_sub(1)))
+            .min(self.config.max_backoff)
+    }
+
+    /// Build a transaction with `factory` from a fresh window and blockhash, sign it, send it in
+    /// the next safe leader run and wait for it to land. Guard aborts are retried up to
+    /// `max_attempts` submissions in total; any other failure is returned as
+    /// [`AntiSandwichError::TransactionFailed`].
+    pub async fn submit<F>(
+        &self,
+        mut factory: F,
+        signers: &[&(dyn Signer + Sync)],
+    ) -> Result<Landed>
+    where
+        F: FnMut(&NefariousWindow, Hash) -> Result<VersionedMessage>,
+    {
+        for attempt in 1..=self.config.max_attempts {
+            let window = self.scheduler.source.window()?;
+            let blockhash = self.scheduler.sender.latest_blockhash().await?;
+            let message = factory(&window, blockhash)?;
+
+            let dyn_signers: Vec<&dyn Signer> =
+                signers.iter().map(|signer| *signer as &dyn Signer).collect();
+            let tx = VersionedTransaction::try_new(message, &dyn_signers)
+                .map_err(|err| AntiSandwichError::Signing(err.to_string()))?;
+            let message = tx.message.clone();
+
+            let submission = self.scheduler.send_when_safe(tx, signers).await?;
+            let Some(err) = self.scheduler.sender.confirm(&submission.signature).await? else {
+                return Ok(Landed { submission, attempts: attempt });
+            };
+
+            match self.client.classify_transaction_error(&err, &message) {
+                TransactionFailure::Aborted { .. } if attempt < self.config.max_attempts => {
+                    self.scheduler.clock.sleep(self.backoff(attempt)).await;
+                }
+                TransactionFailure::Aborted { .. } => break,
+                failure => return Err(AntiSandwichError::TransactionFailed { failure, err }),
+            }
+        }
+        Err(AntiSandwichError::RetriesExhausted { attempts: self.config.max_attempts })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        abort_if_nefarious,
+        scheduler::{
+            tests::{mock_scheduler, MockClock, MockSender},
+            SchedulerConfig,
+        },
+    };
+    use solana_instruction::error::InstructionError;
+    use solana_keypair::Keypair;
+    use solana_message::v0;
+
+    impl ConfirmingSender for MockSender {
+        fn confirm(
+            &self,
+            _signature: &Signature,
+        ) -> impl Future<Output = Result<Option<TransactionError>>> + Send {
+            std::future::ready(Ok(self.outcomes.lock().unwrap().pop_front()))
+        }
+    }
+
+    fn aborted() -> TransactionError {
+        TransactionError::InstructionError(0, InstructionError::Custom(100))
+    }
+
+    fn controller(
+        outcomes: impl IntoIterator<Item = TransactionError>,
+        config: RetryConfig,
+    ) -> Result<RetryController<NefariousWindow, MockClock, MockSender>> {
+        let scheduler = mock_scheduler(
+            NefariousWindow { window_start: 350_000_000, nefarious: [0; 6] },
+            350_000_000,
+            SchedulerConfig::default(),
+        );
+        scheduler.sender.outcomes.lock().unwrap().extend(outcomes);
+        RetryController::new(scheduler, config)
+    }
+
+    #[tokio::test]
+    async fn retries_aborts() {
+        let user = Keypair::new();
+        let mut built = vec![];
+        let factory = |window: &NefariousWindow, blockhash: Hash| {
+            built.push(blockhash);
+            let guard = abort_if_nefarious(window)?;
+            let message = v0::Message::try_compile(&user.pubkey(), &[guard], &[], blockhash)
+                .map_err(|err| AntiSandwichError::InvalidConfig(err.to_string()))?;
+            Ok(VersionedMessage::V0(message))
+        };
+
+        let retry = controller([aborted(), aborted()], RetryConfig::default()).unwrap();
+        let landed = retry.submit(factory, &[&user]).await.unwrap();
+        assert_eq!(landed.attempts, 3);
+        assert_eq!(built.len(), 3);
+        assert_ne!(built[0], built[1]);
+
+        let sent = retry.scheduler.sender.sent.lock().unwrap();
+        assert_eq!(*sent[2].message.recent_blockhash(), built[2]);
+        assert_eq!(landed.submission.signature, sent[2].signatures[0]);
+        // 400ms, then 800ms of backoff
+        assert_eq!(*retry.scheduler.clock.elapsed.lock().unwrap(), Duration::from_millis(1200));
+    }
+
+    #[tokio::test]
+    async fn gives_up() {
+        let user = Keypair::new();
+        let factory = |window: &NefariousWindow, blockhash: Hash| {
+            let guard = abort_if_nefarious(window)?;
+            let message = v0::Message::try_compile(&user.pubkey(), &[guard], &[], blockhash)
+                .map_err(|err| AntiSandwichError::InvalidConfig(err.to_string()))?;
+            Ok(VersionedMessage::V0(message))
+        };
+
+        let config = RetryConfig { max_attempts: 2, ..Default::default() };
+        let exhausted = controller([aborted(), aborted()], config).unwrap();
+        assert_eq!(
+            exhausted.submit(factory, &[&user]).await,
+            Err(AntiSandwichError::RetriesExhausted { attempts: 2 })
+        );
+
+        let unrelated = TransactionError::InstructionError(0, InstructionError::Custom(1));
+        let failed = controller([unrelated.clone()], RetryConfig::default()).unwrap();
+        assert_eq!(
+            failed.submit(factory, &[&user]).await,
+            Err(AntiSandwichError::TransactionFailed {
+                failure: TransactionFailure::Other { index: Some(0) },
+                err: unrelated,
+            })
+        );
+        assert_eq!(failed.scheduler.sender.sent.lock().unwrap().len(), 1);
+
+        let never = RetryConfig { max_attempts: 0, ..Default::default() };
+        assert!(matches!(controller([], never), Err(AntiSandwichError::InvalidConfig(_))));
+    }
+}
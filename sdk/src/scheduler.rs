@@ -0,0 +1,287 @@
+//! Leader-aware submission timing.
+//!
+//! The on-chain guard only acts once a transaction has reached a nefarious leader. A
+//! [`Scheduler`] avoids getting there: it holds a signed transaction until the window shows a run
+//! of unflagged leaders ahead, sends it `lead_time` before that run starts and re-signs it with a
+//! fresh blockhash if the hold outlives the blockhash.
+//!
+//! Time, the current slot and the network are injected through [`Clock`] and
+//! [`TransactionSender`], so the scheduling logic runs against mocks in tests.
+
+use crate::{provider::SlotSource, AntiSandwichError, Result, WindowSource};
+use solana_hash::Hash;
+use solana_signature::Signature;
+use solana_signer::Signer;
+use solana_transaction::versioned::VersionedTransaction;
+use std::{future::Future, time::Duration};
+
+/// Target slot time of the cluster.
+pub const SLOT_DURATION: Duration = Duration::from_millis(400);
+/// Number of slots a blockhash stays valid for (`MAX_PROCESSING_AGE`).
+pub const BLOCKHASH_VALIDITY_SLOTS: u64 = 150;
+
+/// Source of the current slot and of waiting.
+pub trait Clock {
+    fn slot(&self) -> Result<u64>;
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()> + Send;
+}
+
+/// Wall-clock [`Clock`] reading the slot from a [`SlotSource`].
+pub struct SystemClock<S>(pub S);
+
+impl<S: SlotSource + Sync> Clock for SystemClock<S> {
+    fn slot(&self) -> Result<u64> {
+        self.0.slot()
+    }
+
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()> + Send {
+        tokio::time::sleep(duration)
+    }
+}
+
+/// Submits transactions and provides blockhashes to re-sign them with.
+pub trait TransactionSender {
+    fn send(&self, tx: &VersionedTransaction) -> impl Future<Output = Result<Signature>> + Send;
+    fn latest_blockhash(&self) -> impl Future<Output = Result<Hash>> + Send;
+}
+
+#[derive(Clone, Debug)]
+pub struct SchedulerConfig {
+    /// How long before the safe run the transaction is sent, i.e. the expected time to reach the
+    /// leader.
+    pub lead_time: Duration,
+    /// Number of consecutive unflagged slots the transaction must be able to land in.
+    pub safe_slots: u64,
+    /// How often the window is re-read while holding.
+    pub poll_interval: Duration,
+    /// The hold is given up after this long.
+    pub max_hold: Duration,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            lead_time: Duration::from_millis(800),
+            safe_slots: 8,
+            poll_interval: SLOT_DURATION,
+            max_hold: Duration::from_secs(60),
+        }
+    }
+}
+
+/// A transaction sent by [`Scheduler::send_when_safe`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Submission {
+    pub signature: Signature,
+    /// Slot at which the transaction was sent.
+    pub sent_slot: u64,
+    /// First slot of the safe run it was timed for.
+    pub target_slot: u64,
+    /// Whether it was re-signed with a fresh blockhash during the hold.
+    pub resigned: bool,
+}
+
+pub struct Scheduler<W, C, T> {
+    pub(crate) source: W,
+    pub(crate) clock: C,
+    pub(crate) sender: T,
+    config: SchedulerConfig,
+}
+
+impl<W, C, T> Scheduler<W, C, T>
+where
+    W: WindowSource,
+    C: Clock,
+    T: TransactionSender,
+{
+    pub fn new(source: W, clock: C, sender: T, config: SchedulerConfig) -> Self {
+        Self { source, clock, sender, config }
+    }
+
+    fn lead_slots(&self) -> u64 {
+        self.config.lead_time.as_millis().div_ceil(SLOT_DURATION.as_millis()) as u64
+    }
+
+    /// Hold `tx` until it can be sent to land in a safe leader run, then send it.
+    ///
+    /// The blockhash of `tx` is taken to be fresh when the hold starts. If the transaction would
+    /// land more than [`BLOCKHASH_VALIDITY_SLOTS`] later, it is re-signed by `signers` with a
+    /// blockhash from the sender first.
+    pub async fn send_when_safe(
+        &self,
+        mut tx: VersionedTransaction,
+        signers: &[&(dyn Signer + Sync)],
+    ) -> Result<Submission> {
+        let lead = self.lead_slots();
+        let blockhash_slot = self.clock.slot()?;
+        let mut held = Duration::ZERO;
+        let mut resigned = false;
+
+        loop {
+            let slot = self.clock.slot()?;
+            let window = self.source.window()?;
+            let target = window.next_safe_run(slot + lead, self.config.safe_slots);
+
+            if target == Some(slot + lead) {
+                if slot + lead >= blockhash_slot + BLOCKHASH_VALIDITY_SLOTS {
+                    let mut message = tx.message;
+                    message.set_recent_blockhash(self.sender.latest_blockhash().await?);
+                    let signers: Vec<&dyn Signer> =
+                        signers.iter().map(|signer| *signer as &dyn Signer).collect();
+                    tx = VersionedTransaction::try_new(message, &signers)
+                        .map_err(|err| AntiSandwichError::Signing(err.to_string()))?;
+                    resigned = true;
+                }
+                let signature = self.sender.send(&tx).await?;
+                return Ok(Submission {
+                    signature,
+                    sent_slot: slot,
+                    target_slot: slot + lead,
+                    resigned,
+                });
+            }
+
+            if held >= self.config.max_hold {
+                return Err(AntiSandwichError::NoSafeLeaderRun {
+                    slots: self.config.safe_slots,
+                    max_hold: self.config.max_hold,
+                });
+            }
+            // wake up when the run starts, re-reading the window at least every poll interval
+            let wait = match target {
+                Some(target) => SLOT_DURATION * (target - lead - slot) as u32,
+                None => self.config.poll_interval,
+            };
+            let wait = wait.min(self.config.poll_interval).min(self.config.max_hold - held);
+            self.clock.sleep(wait).await;
+            held += wait;
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use anti_sandwich_common::NefariousWindow;
+    use solana_instruction::Instruction;
+    use solana_keypair::Keypair;
+    use solana_message::{v0, VersionedMessage};
+    use solana_pubkey::Pubkey;
+    use solana_transaction_error::TransactionError;
+    use std::{collections::VecDeque, sync::Mutex};
+
+    /// Slots advance with the time slept.
+    pub(crate) struct MockClock {
+        pub(crate) elapsed: Mutex<Duration>,
+        start_slot: u64,
+    }
+
+    impl Clock for MockClock {
+        fn slot(&self) -> Result<u64> {
+            let elapsed = *self.elapsed.lock().unwrap();
+            Ok(self.start_slot + (elapsed.as_millis() / SLOT_DURATION.as_millis()) as u64)
+        }
+
+        fn sleep(&self, duration: Duration) -> impl Future<Output = ()> + Send {
+            *self.elapsed.lock().unwrap() += duration;
+            std::future::ready(())
+        }
+    }
+
+    #[derive(Default)]
+    pub(crate) struct MockSender {
+        pub(crate) sent: Mutex<Vec<VersionedTransaction>>,
+        /// Results handed out by `confirm`, then success.
+        pub(crate) outcomes: Mutex<VecDeque<TransactionError>>,
+    }
+
+    impl TransactionSender for MockSender {
+        fn send(
+            &self,
+            tx: &VersionedTransaction,
+        ) -> impl Future<Output = Result<Signature>> + Send {
+            self.sent.lock().unwrap().push(tx.clone());
+            std::future::ready(Ok(tx.signatures[0]))
+        }
+
+        fn latest_blockhash(&self) -> impl Future<Output = Result<Hash>> + Send {
+            std::future::ready(Ok(Hash::new_unique()))
+        }
+    }
+
+    pub(crate) fn mock_scheduler(
+        window: NefariousWindow,
+        start_slot: u64,
+        config: SchedulerConfig,
+    ) -> Scheduler<NefariousWindow, MockClock, MockSender> {
+        let clock = MockClock { elapsed: Mutex::new(Duration::ZERO), start_slot };
+        Scheduler::new(window, clock, MockSender::default(), config)
+    }
+
+    fn signed(user: &Keypair) -> VersionedTransaction {
+        let ix = Instruction { program_id: Pubkey::new_unique(), accounts: vec![], data: vec![] };
+        let message =
+            v0::Message::try_compile(&user.pubkey(), &[ix], &[], Hash::new_unique()).unwrap();
+        VersionedTransaction::try_new(VersionedMessage::V0(message), &[user]).unwrap()
+    }
+
+    #[tokio::test]
+    async fn holds_until_safe_run() {
+        let user = Keypair::new();
+        // leaders 0..=2 flagged: slots 350_000_000..=350_000_011
+        let window =
+            NefariousWindow { window_start: 350_000_000, nefarious: [0b111, 0, 0, 0, 0, 0] };
+        let scheduler = mock_scheduler(window, 350_000_000, SchedulerConfig::default());
+
+        let tx = signed(&user);
+        let submission = scheduler.send_when_safe(tx.clone(), &[&user]).await.unwrap();
+        assert_eq!(submission.target_slot, 350_000_012);
+        assert_eq!(submission.sent_slot, 350_000_010);
+        assert!(!submission.resigned);
+        assert_eq!(*scheduler.sender.sent.lock().unwrap(), [tx]);
+
+        // safe right away
+        let scheduler = mock_scheduler(window, 350_000_020, SchedulerConfig::default());
+        let submission = scheduler.send_when_safe(signed(&user), &[&user]).await.unwrap();
+        assert_eq!(submission.sent_slot, 350_000_020);
+    }
+
+    #[tokio::test]
+    async fn resigns_after_long_hold() {
+        let user = Keypair::new();
+        let mut nefarious = [0xFF; 6];
+        nefarious[5] = 0b0111_1111; // only the last leader is safe
+        let window = NefariousWindow { window_start: 350_000_000, nefarious };
+        let config = SchedulerConfig {
+            lead_time: Duration::ZERO,
+            safe_slots: 4,
+            max_hold: Duration::from_secs(120),
+            ..Default::default()
+        };
+        let scheduler = mock_scheduler(window, 350_000_000, config);
+
+        let tx = signed(&user);
+        let submission = scheduler.send_when_safe(tx.clone(), &[&user]).await.unwrap();
+        assert_eq!(submission.sent_slot, 350_000_188);
+        assert!(submission.resigned);
+
+        let sent = &scheduler.sender.sent.lock().unwrap()[0];
+        assert_ne!(sent.message.recent_blockhash(), tx.message.recent_blockhash());
+        assert_eq!(sent.signatures, [user.sign_message(&sent.message.serialize())]);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_hold() {
+        let user = Keypair::new();
+        let window = NefariousWindow { window_start: 350_000_000, nefarious: [0xFF; 6] };
+        let config = SchedulerConfig { max_hold: Duration::from_secs(2), ..Default::default() };
+        let scheduler = mock_scheduler(window, 350_000_000, config);
+
+        assert_eq!(
+            scheduler.send_when_safe(signed(&user), &[&user]).await,
+            Err(AntiSandwichError::NoSafeLeaderRun { slots: 8, max_hold: Duration::from_secs(2) })
+        );
+        assert!(scheduler.sender.sent.lock().unwrap().is_empty());
+        assert_eq!(scheduler.clock.slot().unwrap(), 350_000_005);
+    }
+}
@@ -0,0 +1,355 @@
+//! Caching window service for latency-sensitive callers.
+//!
+//! [`WindowService`] keeps the leader schedule of the current and next epoch, the current slot
+//! and the blocklist in memory and refreshes them in background tasks, so handing out a fresh
+//! `NefariousWindow` never waits on the network.
+
+use crate::{
+    provider::{
+        window_at, Blocklist, BlocklistSource, CachedSchedules, LeaderScheduleSource, SlotSource,
+    },
+    AntiSandwichError, Result, WindowSource,
+};
+use anti_sandwich_common::NefariousWindow;
+use std::{
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+use tokio::task::JoinHandle;
+
+#[derive(Clone, Debug)]
+pub struct WindowServiceConfig {
+    /// How often the current slot is polled.
+    pub slot_interval: Duration,
+    /// How often the blocklist is re-fetched.
+    pub blocklist_interval: Duration,
+    /// How often the cached leader schedules are checked for epoch rollover.
+    pub schedule_interval: Duration,
+    /// Windows are refused once the last successful slot update is older than this.
+    pub max_slot_age: Duration,
+    /// Windows are refused once the last successful blocklist update is older than this.
+    pub max_blocklist_age: Duration,
+    /// Blocklist entries below this confidence are left out of windows.
+    pub min_confidence: f64,
+}
+
+impl Default for WindowServiceConfig {
+    fn default() -> Self {
+        Self {
+            slot_interval: Duration::from_millis(400),
+            blocklist_interval: Duration::from_secs(60),
+            schedule_interval: Duration::from_secs(30),
+            max_slot_age: Duration::from_secs(5),
+            max_blocklist_age: Duration::from_secs(600),
+            min_confidence: 0.0,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Cache {
+    slot: Option<(u64, Instant)>,
+    blocklist: Option<(Arc<Blocklist>, Instant)>,
+    schedules: CachedSchedules,
+    /// Error of the last call to each provider, cleared when a call succeeds.
+    errors: [Option<String>; 3],
+}
+
+/// Index into `Cache::errors`.
+#[derive(Copy, Clone)]
+enum Provider {
+    Slot,
+    Blocklist,
+    LeaderSchedule,
+}
+
+struct Inner {
+    leader_schedule: Box<dyn LeaderScheduleSource + Send + Sync>,
+    slot: Box<dyn SlotSource + Send + Sync>,
+    blocklist: Box<dyn BlocklistSource + Send + Sync>,
+    config: WindowServiceConfig,
+    cache: RwLock<Cache>,
+}
+
+impl Inner {
+    fn record<T>(&self, provider: Provider, result: Result<T>) -> Result<T> {
+        self.cache.write().expect("lock poisoned").errors[provider as usize] =
+            result.as_ref().err().map(ToString::to_string);
+        result
+    }
+
+    fn refresh_slot(&self) -> Result<()> {
+        let slot = self.record(Provider::Slot, self.slot.slot())?;
+        let mut cache = self.cache.write().expect("lock poisoned");
+        // never move backwards, e.g. when load-balanced RPC nodes lag behind each other
+        let slot = cache.slot.map_or(slot, |(cached, _)| slot.max(cached));
+        cache.slot = Some((slot, Instant::now()));
+        Ok(())
+    }
+
+    fn refresh_blocklist(&self) -> Result<()> {
+        let blocklist = self.record(Provider::Blocklist, self.blocklist.blocklist())?;
+        self.cache.write().expect("lock poisoned").blocklist =
+            Some((Arc::new(blocklist), Instant::now()));
+        Ok(())
+    }
+
+    /// Make sure the schedules of the current and the next epoch are cached and drop older
+    /// epochs.
+    fn refresh_schedules(&self) -> Result<()> {
+        let (slot, mut schedules) = {
+            let cache = self.cache.read().expect("lock poisoned");
+            let slot = cache
+                .slot
+                .map(|(slot, _)| slot)
+                .ok_or_else(|| AntiSandwichError::StaleData("no slot yet".to_string()))?;
+            (slot, cache.schedules.clone())
+        };
+
+        let refreshed = schedules.refresh(&*self.leader_schedule, slot);
+        self.record(Provider::LeaderSchedule, refreshed)?;
+
+        let mut cache = self.cache.write().expect("lock poisoned");
+        cache.schedules = schedules;
+        Ok(())
+    }
+
+    fn window(&self) -> Result<NefariousWindow> {
+        let cache = self.cache.read().expect("lock poisoned");
+        let reason = |provider: Provider| {
+            cache.errors[provider as usize].as_deref().unwrap_or("not fetched yet").to_string()
+        };
+
+        let (slot, updated) = cache.slot.ok_or_else(|| {
+            AntiSandwichError::StaleData(format!("no slot: {}", reason(Provider::Slot)))
+        })?;
+        if updated.elapsed() > self.config.max_slot_age {
+            let reason = reason(Provider::Slot);
+            return Err(AntiSandwichError::StaleData(format!("slot {slot}: {reason}")));
+        }
+        let (blocklist, updated) = cache.blocklist.as_ref().ok_or_else(|| {
+            AntiSandwichError::StaleData(format!("no blocklist: {}", reason(Provider::Blocklist)))
+        })?;
+        if updated.elapsed() > self.config.max_blocklist_age {
+            let reason = reason(Provider::Blocklist);
+            return Err(AntiSandwichError::StaleData(format!("blocklist: {reason}")));
+        }
+
+        window_at(&cache.schedules, slot, blocklist, self.config.min_confidence)
+    }
+}
+
+/// Hands out `NefariousWindow`s anchored at the current slot from in-memory caches that are
+/// refreshed in the background.
+///
+/// Provider failures are tolerated: the last good data keeps being used until it is older than
+/// the configured `max_*_age`, after which [`WindowService::window`] returns an error that
+/// includes the last provider error.
+pub struct WindowService {
+    inner: Arc<Inner>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl WindowService {
+    /// Fetch the initial slot, blocklist and leader schedules, then spawn the refresh tasks on
+    /// the current tokio runtime. Fails if any of the initial fetches fails.
+    pub async fn start<L, S, B>(
+        leader_schedule: L,
+        slot: S,
+        blocklist: B,
+        config: WindowServiceConfig,
+    ) -> Result<Self>
+    where
+        L: LeaderScheduleSource + Send + Sync + 'static,
+        S: SlotSource + Send + Sync + 'static,
+        B: BlocklistSource + Send + Sync + 'static,
+    {
+        let inner = Arc::new(Inner {
+            leader_schedule: Box::new(leader_schedule),
+            slot: Box::new(slot),
+            blocklist: Box::new(blocklist),
+            config: config.clone(),
+            cache: RwLock::default(),
+        });
+
+        let init = inner.clone();
+        tokio::task::spawn_blocking(move || {
+            init.refresh_slot()?;
+            init.refresh_blocklist()?;
+            init.refresh_schedules()
+        })
+        .await
+        .map_err(|err| AntiSandwichError::Provider(err.to_string()))??;
+
+        let tasks = vec![
+            spawn_refresh(inner.clone(), config.slot_interval, Inner::refresh_slot),
+            spawn_refresh(inner.clone(), config.blocklist_interval, Inner::refresh_blocklist),
+            spawn_refresh(inner.clone(), config.schedule_interval, Inner::refresh_schedules),
+        ];
+
+        Ok(Self { inner, tasks })
+    }
+
+    /// A window anchored at the start of the current leader rotation.
+    pub fn window(&self) -> Result<NefariousWindow> {
+        self.inner.window()
+    }
+
+    /// Last observed slot.
+    pub fn slot(&self) -> Option<u64> {
+        self.inner.cache.read().expect("lock poisoned").slot.map(|(slot, _)| slot)
+    }
+
+    /// Epochs whose leader schedule is currently cached.
+    pub fn cached_epochs(&self) -> Vec<u64> {
+        self.inner.cache.read().expect("lock poisoned").schedules.epochs()
+    }
+
+    /// Errors of the providers whose most recent call failed, if any. An error is cleared once
+    /// the provider succeeds again.
+    pub fn last_error(&self) -> Option<String> {
+        let cache = self.inner.cache.read().expect("lock poisoned");
+        let errors: Vec<_> = cache.errors.iter().flatten().map(String::as_str).collect();
+        (!errors.is_empty()).then(|| errors.join("; "))
+    }
+}
+
+impl WindowSource for WindowService {
+    fn window(&self) -> Result<NefariousWindow> {
+        self.inner.window()
+    }
+}
+
+impl Drop for WindowService {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+fn spawn_refresh(
+    inner: Arc<Inner>,
+    every: Duration,
+    refresh: fn(&Inner) -> Result<()>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(every);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        interval.tick().await; // first tick completes immediately; `start` already refreshed
+
+        loop {
+            interval.tick().await;
+            let inner = inner.clone();
+            // failures are recorded in the cache and surface through `window()` once stale
+            let _ = tokio::task::spawn_blocking(move || refresh(&inner)).await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::{
+        InMemoryBlocklist, InMemoryLeaderSchedule, InMemorySlot, LeaderSchedule,
+    };
+    use solana_pubkey::Pubkey;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    fn epoch(epoch: u64, leader: Pubkey) -> LeaderSchedule {
+        LeaderSchedule { epoch, first_slot: epoch * 400, slot_leaders: vec![leader; 400] }
+    }
+
+    struct Flaky {
+        slot: Arc<InMemorySlot>,
+        down: Arc<AtomicBool>,
+    }
+
+    impl SlotSource for Flaky {
+        fn slot(&self) -> Result<u64> {
+            if self.down.load(Ordering::Relaxed) {
+                return Err(AntiSandwichError::Provider("rpc unreachable".to_string()));
+            }
+            self.slot.slot()
+        }
+    }
+
+    fn config() -> WindowServiceConfig {
+        WindowServiceConfig {
+            slot_interval: Duration::from_millis(5),
+            blocklist_interval: Duration::from_millis(5),
+            schedule_interval: Duration::from_millis(5),
+            max_slot_age: Duration::from_millis(100),
+            max_blocklist_age: Duration::from_secs(60),
+            min_confidence: 0.0,
+        }
+    }
+
+    async fn wait_for(mut done: impl FnMut() -> bool) {
+        for _ in 0..200 {
+            if done() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("condition not reached");
+    }
+
+    #[tokio::test]
+    async fn rolls_over_epochs() {
+        let good = Pubkey::new_unique();
+        let bad = Pubkey::new_unique();
+        let schedules = Arc::new(InMemoryLeaderSchedule::new(vec![epoch(0, good), epoch(1, bad)]));
+        let slot = Arc::new(InMemorySlot::new(302));
+        let blocklist = Arc::new(InMemoryBlocklist::new([bad]));
+
+        let service =
+            WindowService::start(schedules.clone(), slot.clone(), blocklist.clone(), config())
+                .await
+                .unwrap();
+        assert_eq!(service.cached_epochs(), vec![0, 1]);
+
+        // window straddles the epoch boundary at slot 400
+        let window = service.window().unwrap();
+        assert_eq!(window.window_start, 300);
+        assert!(!window.is_nefarious(399));
+        assert!(window.is_nefarious(400));
+
+        // move into epoch 1 and publish epoch 2
+        schedules.insert(epoch(2, good));
+        slot.set(750);
+        wait_for(|| service.cached_epochs() == vec![1, 2]).await;
+        let window = service.window().unwrap();
+        assert_eq!(window.window_start, 748);
+        assert!(window.is_nefarious(799));
+        assert!(!window.is_nefarious(800));
+
+        blocklist.replace([]);
+        wait_for(|| !service.window().unwrap().is_nefarious(750)).await;
+    }
+
+    #[tokio::test]
+    async fn stale_slot_is_refused() {
+        let leader = Pubkey::new_unique();
+        let schedules = InMemoryLeaderSchedule::new(vec![epoch(0, leader), epoch(1, leader)]);
+        let down = Arc::new(AtomicBool::new(false));
+        let slot = Flaky { slot: Arc::new(InMemorySlot::new(10)), down: down.clone() };
+
+        let service = WindowService::start(schedules, slot, InMemoryBlocklist::default(), config())
+            .await
+            .unwrap();
+        assert!(service.window().is_ok());
+
+        // outage: the last good slot is served until it exceeds `max_slot_age`
+        down.store(true, Ordering::Relaxed);
+        assert!(service.window().is_ok());
+        wait_for(|| service.window().is_err()).await;
+        let err = service.window().unwrap_err().to_string();
+        assert!(err.contains("stale data") && err.contains("rpc unreachable"), "{err}");
+        assert_eq!(service.last_error().as_deref(), Some("provider error: rpc unreachable"));
+
+        down.store(false, Ordering::Relaxed);
+        wait_for(|| service.window().is_ok()).await;
+        assert_eq!(service.last_error(), None);
+    }
+}
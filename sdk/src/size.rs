@@ -0,0 +1,269 @@
+//! Serialized size of protected transactions.
+//!
+//! `adjust_slippage_at_runtime` adds 17 bytes of data and the program key to a Jupiter swap, and
+//! complex routes are already close to the packet limit. [`check_transaction_size`] catches
+//! oversized transactions before they are signed and lists what would make them fit. Moving
+//! accounts to a lookup table is the fallback for a single swap; replacing the wrappers with one
+//! abort guard only pays off for transactions with two or more wrapped swaps.
+
+use crate::{
+    client::window_data, message::MessageParts, AntiSandwichClient, AntiSandwichError, Result,
+    ABORT_DISC, ADJUST_SLIPPAGE_DISC, JUPITER_V6, PACKET_DATA_SIZE,
+};
+use anti_sandwich_common::NefariousWindow;
+use solana_message::{compiled_instruction::CompiledInstruction, VersionedMessage};
+use solana_pubkey::Pubkey;
+
+/// Length of the adjust-slippage prefix in front of the Jupiter data.
+const ADJUST_PREFIX_LEN: usize = 1 + NefariousWindow::LEN + 2;
+
+/// A change that brings an oversized transaction under the packet limit, with the resulting
+/// transaction size.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SizeFallback {
+    /// Load these static accounts from an address lookup table instead. Once a Jupiter swap is
+    /// wrapped, the Jupiter program is an account of the wrapper rather than an invoked program,
+    /// so it is usually among them.
+    LookupTable { accounts: Vec<Pubkey>, len: usize },
+    /// Forward the Jupiter instructions unwrapped and fail the whole transaction on a nefarious
+    /// leader with a single `abort_if_nefarious` guard instead. The guard is one byte larger than
+    /// the prefix of one wrapper, so this only shrinks transactions with two or more wrapped
+    /// swaps and is never suggested for one.
+    AbortGuard { len: usize },
+}
+
+/// Number of bytes of a compact-u16 length prefix.
+fn short_vec_len(len: usize) -> usize {
+    match len {
+        0..=0x7f => 1,
+        0x80..=0x3fff => 2,
+        _ => 3,
+    }
+}
+
+/// Size of the signed transaction carrying `message`, signatures included.
+pub fn transaction_size(message: &VersionedMessage) -> usize {
+    let signatures = message.header().num_required_signatures as usize;
+    let keys = message.static_account_keys().len();
+    let instructions = message.instructions();
+
+    let mut len = short_vec_len(signatures) + 64 * signatures;
+    len += 3 + short_vec_len(keys) + 32 * keys + 32;
+    len += short_vec_len(instructions.len());
+    for ix in instructions {
+        len += 1 + short_vec_len(ix.accounts.len()) + ix.accounts.len();
+        len += short_vec_len(ix.data.len()) + ix.data.len();
+    }
+    if let VersionedMessage::V0(message) = message {
+        let lookups = &message.address_table_lookups;
+        len += 1 + short_vec_len(lookups.len());
+        for lookup in lookups {
+            len += 32;
+            len += short_vec_len(lookup.writable_indexes.len()) + lookup.writable_indexes.len();
+            len += short_vec_len(lookup.readonly_indexes.len()) + lookup.readonly_indexes.len();
+        }
+    }
+    len
+}
+
+/// Check the size of a message protected by the upstream program. Use
+/// [`AntiSandwichClient::check_transaction_size`] for other deployments.
+pub fn check_transaction_size(message: &VersionedMessage) -> Result<usize> {
+    AntiSandwichClient::default().check_transaction_size(message)
+}
+
+/// [`SizeFallback::LookupTable`]: move every non-signer static key that isn't an invoked program
+/// into a new lookup table.
+fn lookup_table_fallback(message: &VersionedMessage, len: usize) -> Option<SizeFallback> {
+    let header = message.header();
+    let keys = message.static_account_keys();
+    let signers = header.num_required_signatures as usize;
+    let readonly_from = keys.len() - header.num_readonly_unsigned_accounts as usize;
+
+    let invoked =
+        |i: usize| message.instructions().iter().any(|ix| ix.program_id_index as usize == i);
+    let (writable, readonly): (Vec<_>, Vec<_>) =
+        (signers..keys.len()).filter(|&i| !invoked(i)).partition(|&i| i < readonly_from);
+    let moved = writable.len() + readonly.len();
+    if moved == 0 {
+        return None;
+    }
+
+    // 32 bytes per static key become a one byte lookup index, at the cost of a new table entry
+    let mut new_len = len - 31 * moved - short_vec_len(keys.len())
+        + short_vec_len(keys.len() - moved)
+        + 32
+        + short_vec_len(writable.len())
+        + short_vec_len(readonly.len());
+    match message {
+        // version prefix and the lookup table list
+        VersionedMessage::Legacy(_) => new_len += 2,
+        VersionedMessage::V0(message) => {
+            let lookups = message.address_table_lookups.len();
+            new_len = new_len - short_vec_len(lookups) + short_vec_len(lookups + 1);
+        }
+    }
+
+    (new_len < len).then(|| SizeFallback::LookupTable {
+        accounts: writable.into_iter().chain(readonly).map(|i| keys[i]).collect(),
+        len: new_len,
+    })
+}
+
+impl AntiSandwichClient {
+    /// Serialized size of the signed transaction carrying `message`, or
+    /// [`AntiSandwichError::TransactionTooLarge`] with the applicable [`SizeFallback`]s if it
+    /// exceeds [`PACKET_DATA_SIZE`].
+    pub fn check_transaction_size(&self, message: &VersionedMessage) -> Result<usize> {
+        let len = transaction_size(message);
+        if len <= PACKET_DATA_SIZE {
+            return Ok(len);
+        }
+
+        let fallbacks = [lookup_table_fallback(message, len), self.abort_guard_fallback(message)]
+            .into_iter()
+            .flatten()
+            .filter(|fallback| match fallback {
+                SizeFallback::LookupTable { len, .. } | SizeFallback::AbortGuard { len } => {
+                    *len <= PACKET_DATA_SIZE
+                }
+            })
+            .collect();
+        Err(AntiSandwichError::TransactionTooLarge { len, max: PACKET_DATA_SIZE, fallbacks })
+    }
+
+    /// [`SizeFallback::AbortGuard`]: unwrap every adjust-slippage instruction and put one guard
+    /// with the same window where the first of them was.
+    fn abort_guard_fallback(&self, message: &VersionedMessage) -> Option<SizeFallback> {
+        let mut message = message.clone();
+        let parts = MessageParts::new(&mut message);
+        let jupiter_index = parts.account_keys.iter().position(|key| *key == JUPITER_V6)? as u8;
+
+        let mut first = None;
+        for (i, ix) in parts.instructions.iter_mut().enumerate() {
+            let wrapped = parts.account_keys.get(ix.program_id_index as usize)
+                == Some(&self.program_id)
+                && ix.data.first() == Some(&ADJUST_SLIPPAGE_DISC)
+                && ix.data.len() > ADJUST_PREFIX_LEN;
+            if !wrapped {
+                continue;
+            }
+            let window = NefariousWindow::unpack(&ix.data[1..1 + NefariousWindow::LEN])?;
+            first.get_or_insert((i, ix.program_id_index, window));
+            ix.data.drain(..ADJUST_PREFIX_LEN);
+            ix.program_id_index = jupiter_index;
+        }
+
+        let (at, program_id_index, window) = first?;
+        let guard = CompiledInstruction {
+            program_id_index,
+            accounts: vec![],
+            data: window_data(ABORT_DISC, &window),
+        };
+        parts.instructions.insert(at, guard);
+
+        let len = transaction_size(&message);
+        Some(SizeFallback::AbortGuard { len })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{jupiter::tests::SAMPLE_ROUTE, message::protect_jupiter_message};
+    use solana_hash::Hash;
+    use solana_instruction::{AccountMeta, Instruction};
+    use solana_message::{v0, AddressLookupTableAccount, Message};
+
+    fn jupiter_ix(payer: Pubkey, accounts: usize) -> Instruction {
+        let mut metas = vec![AccountMeta::new(payer, true)];
+        metas.extend((0..accounts).map(|_| AccountMeta::new(Pubkey::new_unique(), false)));
+        metas.push(AccountMeta::new_readonly(JUPITER_V6, false));
+        Instruction { program_id: JUPITER_V6, accounts: metas, data: SAMPLE_ROUTE.to_vec() }
+    }
+
+    #[test]
+    fn matches_bincode() {
+        let payer = Pubkey::new_unique();
+        let ix = jupiter_ix(payer, 3);
+        let alt = AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses: ix.accounts[1..3].iter().map(|meta| meta.pubkey).collect(),
+        };
+
+        let legacy =
+            VersionedMessage::Legacy(Message::new(std::slice::from_ref(&ix), Some(&payer)));
+        let v0 = VersionedMessage::V0(
+            v0::Message::try_compile(&payer, &[ix], &[alt], Hash::default()).unwrap(),
+        );
+        for message in [legacy, v0] {
+            assert_eq!(transaction_size(&message), 1 + 64 + message.serialize().len());
+            let protected =
+                protect_jupiter_message(&message, &NefariousWindow::empty(), 50).unwrap();
+            assert_eq!(transaction_size(&protected), transaction_size(&message) + 32 + 17);
+            assert_eq!(check_transaction_size(&protected), Ok(transaction_size(&protected)));
+        }
+    }
+
+    #[test]
+    fn suggests_fallbacks() {
+        let payer = Pubkey::new_unique();
+        // 30 accounts: fits unwrapped, too large once the program key and prefix are added
+        let message =
+            VersionedMessage::Legacy(Message::new(&[jupiter_ix(payer, 30)], Some(&payer)));
+        let len = transaction_size(&message);
+        assert!(len <= PACKET_DATA_SIZE && len + 32 + 17 > PACKET_DATA_SIZE, "{len}");
+
+        let protected = protect_jupiter_message(&message, &NefariousWindow::empty(), 50).unwrap();
+        let Err(AntiSandwichError::TransactionTooLarge { fallbacks, .. }) =
+            check_transaction_size(&protected)
+        else {
+            panic!("expected TransactionTooLarge");
+        };
+
+        let [SizeFallback::LookupTable { accounts, len: lookup_len }] = &fallbacks[..] else {
+            panic!("unexpected fallbacks {fallbacks:?}");
+        };
+        assert_eq!(accounts.len(), 31);
+        assert!(accounts.contains(&JUPITER_V6));
+        assert!(*lookup_len < len);
+
+        // a separate guard is one byte larger than the wrapper prefix, so it's no fallback here
+        let guarded = AntiSandwichClient::default().abort_guard_fallback(&protected);
+        assert_eq!(
+            guarded,
+            Some(SizeFallback::AbortGuard { len: transaction_size(&protected) + 1 })
+        );
+    }
+
+    #[test]
+    fn suggests_abort_guard_for_several_swaps() {
+        let payer = Pubkey::new_unique();
+        // unwrapping two swaps saves 2 * 17 bytes of prefixes for one 18-byte guard; pad the
+        // message with a memo-like instruction until the protected one is 8 bytes too large
+        let message = |padding: usize| {
+            let filler = Instruction {
+                program_id: Pubkey::new_unique(),
+                accounts: vec![],
+                data: vec![0; padding],
+            };
+            let ixs = [jupiter_ix(payer, 10), jupiter_ix(payer, 10), filler];
+            let message = VersionedMessage::Legacy(Message::new(&ixs, Some(&payer)));
+            protect_jupiter_message(&message, &NefariousWindow::empty(), 50).unwrap()
+        };
+        let protected = (0..PACKET_DATA_SIZE)
+            .map(message)
+            .find(|protected| transaction_size(protected) == PACKET_DATA_SIZE + 8)
+            .unwrap();
+
+        let Err(AntiSandwichError::TransactionTooLarge { fallbacks, .. }) =
+            check_transaction_size(&protected)
+        else {
+            panic!("expected TransactionTooLarge");
+        };
+        assert!(
+            fallbacks.contains(&SizeFallback::AbortGuard { len: PACKET_DATA_SIZE + 8 - 16 }),
+            "{fallbacks:?}"
+        );
+    }
+}
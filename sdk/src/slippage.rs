@@ -0,0 +1,192 @@
+//! `slippage_if_nefarious` derived from a Jupiter quote.
+//!
+//! A sandwich pushes the victim's execution price to the worst price its slippage allows. The
+//! attacker pays the pool fee on both of its legs, so with a tolerance of `t` bps the value it
+//! can take is roughly `t - 2 * pool_fee` bps of the swap.
+//! [`SlippageBudget::slippage_if_nefarious`] picks the tolerance that caps this at `budget_bps`,
+//! using the cheapest pool of the quoted route since that is the one an attacker would sandwich.
+
+use crate::{AntiSandwichError, Result};
+use serde::{de, Deserialize, Deserializer};
+use solana_pubkey::Pubkey;
+use std::str::FromStr;
+
+/// The fields of a Jupiter `/quote` response the derivation uses. Deserializes from the response
+/// JSON, where amounts and the price impact are strings.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Quote {
+    #[serde(deserialize_with = "from_str")]
+    pub in_amount: u64,
+    #[serde(deserialize_with = "from_str")]
+    pub out_amount: u64,
+    pub slippage_bps: u16,
+    /// Price impact as a fraction, `0.01` for 1%.
+    #[serde(deserialize_with = "from_str")]
+    pub price_impact_pct: f64,
+    pub route_plan: Vec<QuoteStep>,
+}
+
+/// One AMM hop of [`Quote::route_plan`].
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuoteStep {
+    pub swap_info: SwapInfo,
+    /// Share of the route's input going through this hop.
+    pub percent: u8,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SwapInfo {
+    #[serde(deserialize_with = "from_str")]
+    pub amm_key: Pubkey,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(deserialize_with = "from_str")]
+    pub input_mint: Pubkey,
+    #[serde(deserialize_with = "from_str")]
+    pub output_mint: Pubkey,
+    #[serde(deserialize_with = "from_str")]
+    pub in_amount: u64,
+    #[serde(deserialize_with = "from_str")]
+    pub out_amount: u64,
+    #[serde(deserialize_with = "from_str")]
+    pub fee_amount: u64,
+    #[serde(deserialize_with = "from_str")]
+    pub fee_mint: Pubkey,
+}
+
+fn from_str<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    let s = String::deserialize(deserializer)?;
+    s.parse().map_err(|err| de::Error::custom(format!("{s}: {err}")))
+}
+
+impl SwapInfo {
+    /// Pool fee of this hop in bps, rounded down. `None` if the fee can't be related to an
+    /// amount of the hop.
+    pub fn fee_bps(&self) -> Option<u16> {
+        let amount = if self.fee_mint == self.input_mint {
+            self.in_amount
+        } else if self.fee_mint == self.output_mint {
+            self.out_amount
+        } else {
+            return None;
+        };
+        (amount > 0).then(|| (self.fee_amount as u128 * 10_000 / amount as u128) as u16)
+    }
+}
+
+impl Quote {
+    pub fn from_json(s: &str) -> Result<Self> {
+        Ok(serde_json::from_str(s)?)
+    }
+
+    pub fn price_impact_bps(&self) -> u16 {
+        (self.price_impact_pct.abs() * 10_000.0).ceil().min(u16::MAX as f64) as u16
+    }
+
+    /// Fee of the cheapest pool on the route, `0` if no hop reports one.
+    pub fn min_pool_fee_bps(&self) -> u16 {
+        self.route_plan.iter().filter_map(|step| step.swap_info.fee_bps()).min().unwrap_or(0)
+    }
+}
+
+/// Bounds for the derived slippage, all in bps.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct SlippageBudget {
+    /// Most of the swap's value a sandwich may take.
+    pub budget_bps: u16,
+    pub floor_bps: u16,
+    pub ceiling_bps: u16,
+}
+
+impl SlippageBudget {
+    /// Fallback slippage for `quote`, to pass to `adjust_slippage_at_runtime` or
+    /// `swap::Protection::AdjustSlippage`.
+    ///
+    /// `budget_bps` plus twice the cheapest pool fee, raised to the quote's price impact so honest
+    /// flow of a similar size doesn't fail the swap, then clamped to `floor_bps..=ceiling_bps`.
+    /// It never exceeds the quote's own `slippage_bps`, which the program would otherwise loosen.
+    pub fn slippage_if_nefarious(&self, quote: &Quote) -> Result<u16> {
+        if self.floor_bps > self.ceiling_bps {
+            return Err(AntiSandwichError::InvalidConfig(format!(
+                "slippage floor {} bps is above the ceiling {} bps",
+                self.floor_bps, self.ceiling_bps
+            )));
+        } else if quote.route_plan.is_empty() {
+            return Err(AntiSandwichError::EmptyInput("route plan"));
+        }
+
+        let derived = self
+            .budget_bps
+            .saturating_add(quote.min_pool_fee_bps().saturating_mul(2))
+            .max(quote.price_impact_bps());
+        Ok(derived.clamp(self.floor_bps, self.ceiling_bps).min(quote.slippage_bps))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOL: &str = "So11111111111111111111111111111111111111112";
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+    const USDT: &str = "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB";
+
+    fn step(input: &str, output: &str, in_amount: u64, out_amount: u64, fee: u64) -> String {
+        format!(
+            r#"{{"swapInfo": {{"ammKey": "{}", "label": "Whirlpool", "inputMint": "{input}",
+                "outputMint": "{output}", "inAmount": "{in_amount}", "outAmount": "{out_amount}",
+                "feeAmount": "{fee}", "feeMint": "{input}"}}, "percent": 100}}"#,
+            Pubkey::new_unique()
+        )
+    }
+
+    fn quote(price_impact: &str, slippage_bps: u16, steps: &[String]) -> Quote {
+        Quote::from_json(&format!(
+            r#"{{"inputMint": "{SOL}", "inAmount": "20000000000", "outputMint": "{USDT}",
+                "outAmount": "3421560000", "otherAmountThreshold": "3216266400",
+                "swapMode": "ExactIn",
+                "slippageBps": {slippage_bps}, "priceImpactPct": "{price_impact}",
+                "routePlan": [{}], "contextSlot": 353612620, "timeTaken": 0.01}}"#,
+            steps.join(",")
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn derives_from_route() {
+        // 4 bps and 1 bps pools, the attacker goes for the 1 bps one
+        let route = [
+            step(SOL, USDC, 20_000_000_000, 3_422_000_000, 8_000_000),
+            step(USDC, USDT, 3_422_000_000, 3_421_560_000, 342_200),
+        ];
+        let budget = SlippageBudget { budget_bps: 30, floor_bps: 10, ceiling_bps: 300 };
+
+        let deep = quote("0.0001", 600, &route);
+        assert_eq!(deep.min_pool_fee_bps(), 1);
+        assert_eq!(deep.price_impact_bps(), 1);
+        assert_eq!(budget.slippage_if_nefarious(&deep), Ok(32));
+
+        // honest flow of the same size moves the price by the quoted impact
+        assert_eq!(budget.slippage_if_nefarious(&quote("0.0125", 600, &route)), Ok(125));
+        assert_eq!(budget.slippage_if_nefarious(&quote("0.05", 600, &route)), Ok(300));
+        assert_eq!(budget.slippage_if_nefarious(&quote("0.0001", 20, &route)), Ok(20));
+
+        let floored = SlippageBudget { budget_bps: 0, floor_bps: 10, ceiling_bps: 300 };
+        assert_eq!(floored.slippage_if_nefarious(&deep), Ok(10));
+
+        let inverted = SlippageBudget { budget_bps: 30, floor_bps: 300, ceiling_bps: 10 };
+        assert!(inverted.slippage_if_nefarious(&deep).is_err());
+        assert_eq!(
+            budget.slippage_if_nefarious(&quote("0", 600, &[])),
+            Err(AntiSandwichError::EmptyInput("route plan"))
+        );
+    }
+}
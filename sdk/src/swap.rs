@@ -0,0 +1,191 @@
+//! One-call protection for a Jupiter `/swap-instructions` response.
+//!
+//! [`SwapInstructions`] mirrors the response of the Jupiter swap API. With the `jupiter-api`
+//! feature, `jupiter_swap_api_client::swap::SwapInstructionsResponse` converts into it directly.
+
+use crate::{jupiter::RouteArgs, AntiSandwichClient, Result, WindowSource};
+use serde::{Deserialize, Serialize};
+use solana_instruction::Instruction;
+use solana_pubkey::Pubkey;
+
+/// How a swap is protected when it lands on a nefarious leader.
+///
+/// Serialized with an `action` tag, e.g.
+/// `{"action": "adjust_slippage", "slippage_if_nefarious": 50}`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Protection {
+    /// Wrap the swap with `adjust_slippage_at_runtime`, lowering its slippage to
+    /// `slippage_if_nefarious` bps. A route quoted with tighter slippage keeps its own.
+    AdjustSlippage { slippage_if_nefarious: u16 },
+    /// Leave the swap untouched and fail the whole transaction with an `abort_if_nefarious`
+    /// guard.
+    Abort,
+    /// Leave the swap unprotected.
+    None,
+}
+
+/// Instructions returned by the Jupiter swap API for one swap.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SwapInstructions {
+    pub compute_budget_instructions: Vec<Instruction>,
+    pub other_instructions: Vec<Instruction>,
+    pub setup_instructions: Vec<Instruction>,
+    pub token_ledger_instruction: Option<Instruction>,
+    pub swap_instruction: Instruction,
+    pub cleanup_instruction: Option<Instruction>,
+    pub address_lookup_table_addresses: Vec<Pubkey>,
+}
+
+impl SwapInstructions {
+    /// A swap instruction without any surrounding instructions or lookup tables.
+    pub fn new(swap_instruction: Instruction) -> Self {
+        Self {
+            compute_budget_instructions: vec![],
+            other_instructions: vec![],
+            setup_instructions: vec![],
+            token_ledger_instruction: None,
+            swap_instruction,
+            cleanup_instruction: None,
+            address_lookup_table_addresses: vec![],
+        }
+    }
+}
+
+#[cfg(feature = "jupiter-api")]
+impl From<jupiter_swap_api_client::swap::SwapInstructionsResponse> for SwapInstructions {
+    fn from(resp: jupiter_swap_api_client::swap::SwapInstructionsResponse) -> Self {
+        Self {
+            compute_budget_instructions: resp.compute_budget_instructions,
+            other_instructions: resp.other_instructions,
+            setup_instructions: resp.setup_instructions,
+            token_ledger_instruction: resp.token_ledger_instruction,
+            swap_instruction: resp.swap_instruction,
+            cleanup_instruction: resp.cleanup_instruction,
+            address_lookup_table_addresses: resp.address_lookup_table_addresses,
+        }
+    }
+}
+
+/// Ready-to-compile transaction contents of a protected swap.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProtectedSwap {
+    pub instructions: Vec<Instruction>,
+    pub address_lookup_table_addresses: Vec<Pubkey>,
+}
+
+/// Protect a swap from the Jupiter swap API against the upstream program. Use
+/// [`AntiSandwichClient::protect_swap`] for other deployments.
+pub fn protect_swap<W: WindowSource + ?Sized>(
+    source: &W,
+    swap: impl Into<SwapInstructions>,
+    protection: Protection,
+) -> Result<ProtectedSwap> {
+    AntiSandwichClient::default().protect_swap(source, swap, protection)
+}
+
+impl AntiSandwichClient {
+    /// Full, ordered instruction list for a swap: compute budget, other, setup and token ledger
+    /// instructions, then the protected swap and the cleanup instruction. The compute unit limit
+    /// is raised by the protection's overhead; [`Protection::None`] leaves the instructions as
+    /// they are.
+    pub fn protect_swap<W: WindowSource + ?Sized>(
+        &self,
+        source: &W,
+        swap: impl Into<SwapInstructions>,
+        protection: Protection,
+    ) -> Result<ProtectedSwap> {
+        let swap = swap.into();
+        let mut instructions = swap.compute_budget_instructions;
+        instructions.extend(swap.other_instructions);
+        instructions.extend(swap.setup_instructions);
+        instructions.extend(swap.token_ledger_instruction);
+
+        match protection {
+            Protection::AdjustSlippage { slippage_if_nefarious } => {
+                // the program overwrites the route's slippage, which must not loosen it
+                let ix = &swap.swap_instruction;
+                let quoted = RouteArgs::slippage_bps_of(&ix.program_id, &ix.data)?;
+                instructions.push(self.adjust_slippage_at_runtime(
+                    source,
+                    slippage_if_nefarious.min(quoted),
+                    swap.swap_instruction,
+                )?);
+            }
+            Protection::Abort => {
+                // the guard still validates the route so both protections accept the same input
+                let ix = &swap.swap_instruction;
+                crate::check_jupiter_route(&ix.program_id, &ix.data)?;
+                instructions.push(self.abort_if_nefarious(source)?);
+                instructions.push(swap.swap_instruction);
+            }
+            Protection::None => instructions.push(swap.swap_instruction),
+        }
+
+        instructions.extend(swap.cleanup_instruction);
+        if protection != Protection::None {
+            self.adjust_compute_budget(&mut instructions);
+        }
+
+        Ok(ProtectedSwap {
+            instructions,
+            address_lookup_table_addresses: swap.address_lookup_table_addresses,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        compute_budget::{compute_unit_limit, set_compute_unit_limit, COMPUTE_BUDGET_PROGRAM},
+        jupiter::tests::SAMPLE_ROUTE,
+        ABORT_DISC, ADJUST_SLIPPAGE_DISC, JUPITER_V6, PROGRAM_ID,
+    };
+    use anti_sandwich_common::NefariousWindow;
+
+    fn ix(program_id: Pubkey) -> Instruction {
+        Instruction { program_id, accounts: vec![], data: vec![] }
+    }
+
+    #[test]
+    fn orders_and_protects() {
+        let (setup, cleanup, alt) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let swap = SwapInstructions {
+            compute_budget_instructions: vec![set_compute_unit_limit(300_000)],
+            setup_instructions: vec![ix(setup)],
+            cleanup_instruction: Some(ix(cleanup)),
+            address_lookup_table_addresses: vec![alt],
+            ..SwapInstructions::new(Instruction { data: SAMPLE_ROUTE.to_vec(), ..ix(JUPITER_V6) })
+        };
+        let window = NefariousWindow::empty();
+
+        let adjusted = protect_swap(
+            &window,
+            swap.clone(),
+            Protection::AdjustSlippage { slippage_if_nefarious: 50 },
+        )
+        .unwrap();
+        let programs: Vec<_> = adjusted.instructions.iter().map(|ix| ix.program_id).collect();
+        assert_eq!(programs, [COMPUTE_BUDGET_PROGRAM, setup, PROGRAM_ID, cleanup]);
+        assert_eq!(adjusted.instructions[2].data[0], ADJUST_SLIPPAGE_DISC);
+        assert_eq!(compute_unit_limit(&adjusted.instructions), Some((0, 301_500)));
+        assert_eq!(adjusted.address_lookup_table_addresses, [alt]);
+
+        let aborted = protect_swap(&window, swap.clone(), Protection::Abort).unwrap();
+        let programs: Vec<_> = aborted.instructions.iter().map(|ix| ix.program_id).collect();
+        assert_eq!(programs, [COMPUTE_BUDGET_PROGRAM, setup, PROGRAM_ID, JUPITER_V6, cleanup]);
+        assert_eq!(aborted.instructions[2].data[0], ABORT_DISC);
+        assert_eq!(aborted.instructions[3], swap.swap_instruction);
+        assert_eq!(compute_unit_limit(&aborted.instructions), Some((0, 300_300)));
+
+        let unprotected = protect_swap(&window, swap.clone(), Protection::None).unwrap();
+        let programs: Vec<_> = unprotected.instructions.iter().map(|ix| ix.program_id).collect();
+        assert_eq!(programs, [COMPUTE_BUDGET_PROGRAM, setup, JUPITER_V6, cleanup]);
+        assert_eq!(compute_unit_limit(&unprotected.instructions), Some((0, 300_000)));
+
+        let not_a_route = SwapInstructions { swap_instruction: ix(JUPITER_V6), ..swap };
+        assert!(protect_swap(&window, not_a_route, Protection::Abort).is_err());
+    }
+}
@@ -0,0 +1,399 @@
+//! Push-based window updates.
+//!
+//! [`WindowUpdates`] follows a slot ticker, e.g. a `slotSubscribe` feed or [`poll_slots`], and
+//! recomputes the window whenever the slot enters a new leader rotation or the blocklist is
+//! refreshed. A window is published through a `tokio::sync::watch` channel only when it differs
+//! from the last one, so subscribers are woken up for actual changes.
+
+use crate::{
+    provider::{
+        window_at, Blocklist, BlocklistSource, CachedSchedules, LeaderScheduleSource, SlotSource,
+        SLOTS_PER_LEADER,
+    },
+    AntiSandwichError, Result, WindowSource,
+};
+use anti_sandwich_common::NefariousWindow;
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::{
+    sync::{mpsc, watch},
+    task::JoinHandle,
+    time::MissedTickBehavior,
+};
+use tokio_stream::{
+    wrappers::{ReceiverStream, WatchStream},
+    Stream, StreamExt,
+};
+
+#[derive(Clone, Debug)]
+pub struct WindowUpdatesConfig {
+    /// How often the blocklist is re-fetched.
+    pub blocklist_interval: Duration,
+    /// Once the last successful blocklist refresh is older than this, a `StaleData` error is
+    /// published instead of a window.
+    pub max_blocklist_age: Duration,
+    /// Blocklist entries below this confidence are left out of windows.
+    pub min_confidence: f64,
+}
+
+impl Default for WindowUpdatesConfig {
+    fn default() -> Self {
+        Self {
+            blocklist_interval: Duration::from_secs(60),
+            max_blocklist_age: Duration::from_secs(600),
+            min_confidence: 0.0,
+        }
+    }
+}
+
+struct Inner<L, B> {
+    leader_schedule: L,
+    /// Schedules of the current and the next epoch, fetched as the slot moves on.
+    schedules: Mutex<CachedSchedules>,
+    blocklist: B,
+    config: WindowUpdatesConfig,
+}
+
+impl<L: LeaderScheduleSource, B> Inner<L, B> {
+    fn window(&self, slot: u64, blocklist: &Blocklist) -> Result<NefariousWindow> {
+        let mut schedules = self.schedules.lock().expect("lock poisoned");
+        schedules.refresh(&self.leader_schedule, slot)?;
+        window_at(&*schedules, slot, blocklist, self.config.min_confidence)
+    }
+}
+
+/// Run `f` on the blocking pool, as the sources may do network I/O.
+async fn blocking<L, B, T>(
+    inner: &Arc<Inner<L, B>>,
+    f: impl FnOnce(&Inner<L, B>) -> Result<T> + Send + 'static,
+) -> Result<T>
+where
+    L: Send + Sync + 'static,
+    B: Send + Sync + 'static,
+    T: Send + 'static,
+{
+    let inner = inner.clone();
+    tokio::task::spawn_blocking(move || f(&inner))
+        .await
+        .map_err(|err| AntiSandwichError::Provider(err.to_string()))?
+}
+
+/// Windows anchored at the start of the current leader rotation, pushed to subscribers as they
+/// change.
+///
+/// A window that can't be built, e.g. because the leader schedule of the slot is unavailable, is
+/// published as an error and replaced once the next slot or blocklist refresh succeeds. A failed
+/// blocklist refresh keeps the previous blocklist until it is older than
+/// [`WindowUpdatesConfig::max_blocklist_age`]; from then on a `StaleData` error carrying the last
+/// refresh error is published until a refresh succeeds. Updates end when the slot ticker ends.
+pub struct WindowUpdates {
+    receiver: watch::Receiver<Result<NefariousWindow>>,
+    task: JoinHandle<()>,
+}
+
+impl WindowUpdates {
+    /// Wait for the first slot of `slots`, fetch the blocklist and build the first window, then
+    /// spawn the update task on the current tokio runtime. Fails if the first window can't be
+    /// built.
+    pub async fn start<L, T, B>(
+        leader_schedule: L,
+        mut slots: T,
+        blocklist: B,
+        config: WindowUpdatesConfig,
+    ) -> Result<Self>
+    where
+        L: LeaderScheduleSource + Send + Sync + 'static,
+        T: Stream<Item = u64> + Send + Unpin + 'static,
+        B: BlocklistSource + Send + Sync + 'static,
+    {
+        let inner =
+            Arc::new(Inner { leader_schedule, schedules: Mutex::default(), blocklist, config });
+
+        let slot = slots
+            .next()
+            .await
+            .ok_or_else(|| AntiSandwichError::Provider("slot ticker ended".to_string()))?;
+        let (blocklist, window) = blocking(&inner, move |inner| {
+            let blocklist = inner.blocklist.blocklist()?;
+            let window = inner.window(slot, &blocklist)?;
+            Ok((Arc::new(blocklist), window))
+        })
+        .await?;
+
+        let (sender, receiver) = watch::channel(Ok(window));
+        let task = tokio::spawn(run(inner, slots, sender, slot, blocklist));
+        Ok(Self { receiver, task })
+    }
+
+    /// A receiver that sees the latest window and is notified when it changes.
+    pub fn subscribe(&self) -> watch::Receiver<Result<NefariousWindow>> {
+        self.receiver.clone()
+    }
+
+    /// The latest window first, then every change.
+    pub fn stream(&self) -> WatchStream<Result<NefariousWindow>> {
+        WatchStream::new(self.receiver.clone())
+    }
+
+    pub fn latest(&self) -> Result<NefariousWindow> {
+        self.receiver.borrow().clone()
+    }
+}
+
+impl WindowSource for WindowUpdates {
+    fn window(&self) -> Result<NefariousWindow> {
+        self.latest()
+    }
+}
+
+impl Drop for WindowUpdates {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn run<L, T, B>(
+    inner: Arc<Inner<L, B>>,
+    mut slots: T,
+    sender: watch::Sender<Result<NefariousWindow>>,
+    mut slot: u64,
+    mut blocklist: Arc<Blocklist>,
+) where
+    L: LeaderScheduleSource + Send + Sync + 'static,
+    T: Stream<Item = u64> + Unpin,
+    B: BlocklistSource + Send + Sync + 'static,
+{
+    let mut refresh = tokio::time::interval(inner.config.blocklist_interval);
+    refresh.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    refresh.tick().await; // first tick completes immediately; `start` already fetched
+    let mut rotation_start = sender.borrow().as_ref().ok().map(|window| window.window_start);
+    let mut refreshed_at = Instant::now();
+    let mut refresh_error = None;
+
+    loop {
+        tokio::select! {
+            next = slots.next() => {
+                let Some(next) = next else { break };
+                // never move backwards, e.g. when load-balanced RPC nodes lag behind each other
+                if next <= slot {
+                    continue;
+                }
+                slot = next;
+                if rotation_start.is_some_and(|start| slot < start + SLOTS_PER_LEADER) {
+                    continue;
+                }
+            }
+            _ = refresh.tick() => {
+                match blocking(&inner, |inner| inner.blocklist.blocklist()).await {
+                    Ok(refreshed) => {
+                        blocklist = Arc::new(refreshed);
+                        refreshed_at = Instant::now();
+                        refresh_error = None;
+                    }
+                    Err(err) => {
+                        refresh_error = Some(err.to_string());
+                        if refreshed_at.elapsed() <= inner.config.max_blocklist_age {
+                            continue;
+                        }
+                    }
+                }
+            }
+            _ = sender.closed() => break,
+        }
+
+        let window = if refreshed_at.elapsed() > inner.config.max_blocklist_age {
+            let reason = refresh_error.as_deref().unwrap_or("not refreshed");
+            Err(AntiSandwichError::StaleData(format!("blocklist: {reason}")))
+        } else {
+            let current = blocklist.clone();
+            blocking(&inner, move |inner| inner.window(slot, &current)).await
+        };
+        rotation_start = window.as_ref().ok().map(|window| window.window_start);
+        sender.send_if_modified(|published| {
+            let changed = *published != window;
+            *published = window;
+            changed
+        });
+    }
+}
+
+/// Slot ticker that polls `source` every `every`, for slot sources without a subscription.
+/// Failed polls are skipped.
+pub fn poll_slots<S>(source: S, every: Duration) -> ReceiverStream<u64>
+where
+    S: SlotSource + Send + Sync + 'static,
+{
+    let (sender, receiver) = mpsc::channel(16);
+    let source = Arc::new(source);
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(every);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        while !sender.is_closed() {
+            interval.tick().await;
+            let source = source.clone();
+            if let Ok(Ok(slot)) = tokio::task::spawn_blocking(move || source.slot()).await {
+                if sender.send(slot).await.is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    ReceiverStream::new(receiver)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::{
+        InMemoryBlocklist, InMemoryLeaderSchedule, InMemorySlot, LeaderSchedule,
+    };
+    use solana_pubkey::Pubkey;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    /// Two epochs whose leaders alternate between `good` and `bad` every rotation.
+    fn schedules(good: Pubkey, bad: Pubkey) -> InMemoryLeaderSchedule {
+        let slot_leaders: Vec<_> =
+            (0..400).map(|i| if (i / 4) % 2 == 0 { good } else { bad }).collect();
+        InMemoryLeaderSchedule::new(
+            (0..2)
+                .map(|epoch| LeaderSchedule {
+                    epoch,
+                    first_slot: epoch * 400,
+                    slot_leaders: slot_leaders.clone(),
+                })
+                .collect(),
+        )
+    }
+
+    /// A slot ticker driven by the test.
+    fn ticker() -> (mpsc::Sender<u64>, ReceiverStream<u64>) {
+        let (sender, receiver) = mpsc::channel(16);
+        (sender, ReceiverStream::new(receiver))
+    }
+
+    async fn next<S: Stream<Item = Result<NefariousWindow>> + Unpin>(
+        stream: &mut S,
+    ) -> Option<Result<NefariousWindow>> {
+        tokio::time::timeout(Duration::from_secs(1), stream.next()).await.expect("no update")
+    }
+
+    fn config() -> WindowUpdatesConfig {
+        WindowUpdatesConfig { blocklist_interval: Duration::from_millis(5), ..Default::default() }
+    }
+
+    #[tokio::test]
+    async fn pushes_new_rotations() {
+        let [good, bad] = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let (slots, ticker) = ticker();
+        slots.send(97).await.unwrap();
+
+        let blocklist = InMemoryBlocklist::new([bad]);
+        let config =
+            WindowUpdatesConfig { blocklist_interval: Duration::from_secs(60), ..config() };
+        let updates =
+            WindowUpdates::start(schedules(good, bad), ticker, blocklist, config).await.unwrap();
+        let mut stream = updates.stream();
+
+        let window = next(&mut stream).await.unwrap().unwrap();
+        assert_eq!(window.window_start, 96);
+        assert!(!window.is_nefarious(96));
+        assert!(window.is_nefarious(100));
+
+        // the rotation of slot 96 ends at 99
+        for slot in [98, 99, 97, 101] {
+            slots.send(slot).await.unwrap();
+        }
+        let window = next(&mut stream).await.unwrap().unwrap();
+        assert_eq!(window.window_start, 100);
+        assert!(window.is_nefarious(100));
+        assert_eq!(updates.latest(), Ok(window));
+
+        // beyond the leader schedule
+        slots.send(800).await.unwrap();
+        assert!(matches!(next(&mut stream).await, Some(Err(AntiSandwichError::Provider(_)))));
+        slots.send(801).await.unwrap();
+        assert!(matches!(next(&mut stream).await, Some(Err(AntiSandwichError::Provider(_)))));
+
+        drop(slots);
+        assert_eq!(next(&mut stream).await, None);
+    }
+
+    #[tokio::test]
+    async fn pushes_blocklist_changes() {
+        let [good, bad] = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let (slots, ticker) = ticker();
+        slots.send(0).await.unwrap();
+
+        let blocklist = Arc::new(InMemoryBlocklist::default());
+        let updates =
+            WindowUpdates::start(schedules(good, bad), ticker, blocklist.clone(), config())
+                .await
+                .unwrap();
+        let mut receiver = updates.subscribe();
+        assert_eq!(updates.latest().unwrap().nefarious, [0; 6]);
+
+        blocklist.replace([bad]);
+        receiver.changed().await.unwrap();
+        assert_eq!(receiver.borrow_and_update().as_ref().unwrap().nefarious, [0xAA; 6]);
+
+        // refreshes that don't change the window are not pushed
+        blocklist.replace([bad, Pubkey::new_unique()]);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!receiver.has_changed().unwrap());
+    }
+
+    struct Flaky {
+        blocklist: InMemoryBlocklist,
+        down: AtomicBool,
+    }
+
+    impl BlocklistSource for Flaky {
+        fn blocklist(&self) -> Result<Blocklist> {
+            if self.down.load(Ordering::Relaxed) {
+                return Err(AntiSandwichError::Provider("feed unreachable".to_string()));
+            }
+            self.blocklist.blocklist()
+        }
+    }
+
+    #[tokio::test]
+    async fn publishes_stale_blocklist() {
+        let [good, bad] = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let (slots, ticker) = ticker();
+        slots.send(0).await.unwrap();
+
+        let blocklist = Arc::new(Flaky {
+            blocklist: InMemoryBlocklist::new([bad]),
+            down: AtomicBool::new(false),
+        });
+        let config =
+            WindowUpdatesConfig { max_blocklist_age: Duration::from_millis(30), ..config() };
+        let updates = WindowUpdates::start(schedules(good, bad), ticker, blocklist.clone(), config)
+            .await
+            .unwrap();
+        let mut stream = updates.stream();
+        assert_eq!(next(&mut stream).await.unwrap().unwrap().nefarious, [0xAA; 6]);
+
+        blocklist.down.store(true, Ordering::Relaxed);
+        let Some(Err(AntiSandwichError::StaleData(reason))) = next(&mut stream).await else {
+            panic!("expected stale data");
+        };
+        assert!(reason.contains("feed unreachable"), "{reason}");
+
+        blocklist.down.store(false, Ordering::Relaxed);
+        assert_eq!(next(&mut stream).await.unwrap().unwrap().nefarious, [0xAA; 6]);
+    }
+
+    #[tokio::test]
+    async fn polls_slot_source() {
+        let slot = Arc::new(InMemorySlot::new(7));
+        let mut ticker = poll_slots(slot.clone(), Duration::from_millis(5));
+        assert_eq!(ticker.next().await, Some(7));
+        slot.set(8);
+        while ticker.next().await != Some(8) {}
+    }
+}